@@ -0,0 +1,1556 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+use zellij_tile::prelude::{InputMode, Palette, PaletteColor};
+
+const SUPERSCRIPT_DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+const SUBSCRIPT_DIGITS: [char; 10] = ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉'];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NumberStyle {
+    #[default]
+    Normal,
+    Superscript,
+    Subscript,
+}
+
+impl NumberStyle {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "superscript" => NumberStyle::Superscript,
+            "subscript" => NumberStyle::Subscript,
+            _ => NumberStyle::Normal,
+        }
+    }
+
+    // maps every digit of the number to its glyph, all of them are 1 column wide
+    pub fn format(&self, number: usize) -> String {
+        let digits = match self {
+            NumberStyle::Normal => return number.to_string(),
+            NumberStyle::Superscript => &SUPERSCRIPT_DIGITS,
+            NumberStyle::Subscript => &SUBSCRIPT_DIGITS,
+        };
+        number
+            .to_string()
+            .chars()
+            .filter_map(|c| c.to_digit(10))
+            .map(|d| digits[d as usize])
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    #[default]
+    Right,
+}
+
+impl Side {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "left" => Side::Left,
+            _ => Side::Right,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityCue {
+    #[default]
+    Off,
+    Bell,
+    Flash,
+}
+
+impl ActivityCue {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "bell" => ActivityCue::Bell,
+            "flash" => ActivityCue::Flash,
+            _ => ActivityCue::Off,
+        }
+    }
+}
+
+// which files the layout cache is written to, kdl is read back when it is the only one
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CacheFormat {
+    #[default]
+    Json,
+    Kdl,
+    Both,
+}
+
+impl CacheFormat {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "kdl" => CacheFormat::Kdl,
+            "both" => CacheFormat::Both,
+            _ => CacheFormat::Json,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Density {
+    #[default]
+    Compact,
+    Verbose,
+}
+
+impl Density {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "verbose" => Density::Verbose,
+            _ => Density::Compact,
+        }
+    }
+}
+
+// ordered from quiet to verbose, a message is shown when its level is at most this
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Off,
+    #[default]
+    Error,
+    Debug,
+}
+
+impl LogLevel {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "off" => LogLevel::Off,
+            "debug" => LogLevel::Debug,
+            _ => LogLevel::Error,
+        }
+    }
+}
+
+// what a right click on a tab does
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RightClickAction {
+    #[default]
+    Nothing,
+    Close,
+    ToggleSync,
+}
+
+impl RightClickAction {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "close" => RightClickAction::Close,
+            "toggle_sync" => RightClickAction::ToggleSync,
+            _ => RightClickAction::Nothing,
+        }
+    }
+}
+
+// what a left click on the tab that is already active does
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ActiveTabClick {
+    #[default]
+    Nothing,
+    Rename,
+    ToggleFullscreen,
+}
+
+impl ActiveTabClick {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "rename" => ActiveTabClick::Rename,
+            "toggle_fullscreen" => ActiveTabClick::ToggleFullscreen,
+            _ => ActiveTabClick::Nothing,
+        }
+    }
+}
+
+// clicks on the bar past the last tab that open a new tab
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyClick {
+    #[default]
+    Off,
+    Single,
+    Double,
+}
+
+impl EmptyClick {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "single" => EmptyClick::Single,
+            "double" | "true" => EmptyClick::Double,
+            _ => EmptyClick::Off,
+        }
+    }
+}
+
+// what a click on the name of a tab does, clicks on its number always focus it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NameClick {
+    #[default]
+    Focus,
+    Rename,
+}
+
+impl NameClick {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "rename" => NameClick::Rename,
+            _ => NameClick::Focus,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollAction {
+    #[default]
+    SwitchTab,
+    // pans the visible tabs without changing focus, even past the active tab
+    PageWindow,
+    // goes to the next or previous session like the switch_session pipes
+    SwitchSession,
+}
+
+impl ScrollAction {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "page_window" => ScrollAction::PageWindow,
+            "sessions" => ScrollAction::SwitchSession,
+            _ => ScrollAction::SwitchTab,
+        }
+    }
+}
+
+// a color from the theme, looked up when drawing since the palette only arrives
+// with the first mode update, or a fixed one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSetting {
+    Fg,
+    Bg,
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    Orange,
+    Gray,
+    Fixed(PaletteColor),
+}
+
+impl ColorSetting {
+    fn from_config(value: &str) -> Option<Self> {
+        let setting = match value.trim().to_lowercase().as_str() {
+            "fg" => ColorSetting::Fg,
+            "bg" => ColorSetting::Bg,
+            "black" => ColorSetting::Black,
+            "red" => ColorSetting::Red,
+            "green" => ColorSetting::Green,
+            "yellow" => ColorSetting::Yellow,
+            "blue" => ColorSetting::Blue,
+            "magenta" => ColorSetting::Magenta,
+            "cyan" => ColorSetting::Cyan,
+            "white" => ColorSetting::White,
+            "orange" => ColorSetting::Orange,
+            "gray" | "grey" => ColorSetting::Gray,
+            _ => ColorSetting::Fixed(parse_color(value)?),
+        };
+        Some(setting)
+    }
+
+    pub fn resolve(&self, palette: Palette) -> PaletteColor {
+        match self {
+            ColorSetting::Fg => palette.fg,
+            ColorSetting::Bg => palette.bg,
+            ColorSetting::Black => palette.black,
+            ColorSetting::Red => palette.red,
+            ColorSetting::Green => palette.green,
+            ColorSetting::Yellow => palette.yellow,
+            ColorSetting::Blue => palette.blue,
+            ColorSetting::Magenta => palette.magenta,
+            ColorSetting::Cyan => palette.cyan,
+            ColorSetting::White => palette.white,
+            ColorSetting::Orange => palette.orange,
+            ColorSetting::Gray => palette.gray,
+            ColorSetting::Fixed(color) => *color,
+        }
+    }
+}
+
+/// An ordered list of preferred glyphs, configured as a comma separated list.
+///
+/// There is no way for a plugin to know which glyphs the terminal font can
+/// actually draw, so the first entry is always used until the user cycles to
+/// the next one with the `cycle_glyphs` pipe message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyph {
+    choices: Vec<String>,
+    selected: usize,
+}
+
+impl Glyph {
+    fn new(default: &str) -> Self {
+        Glyph {
+            choices: vec![default.to_string()],
+            selected: 0,
+        }
+    }
+
+    fn from_config(value: &str, default: &str) -> Self {
+        let choices: Vec<String> = value
+            .split(',')
+            .map(|choice| choice.trim())
+            .filter(|choice| !choice.is_empty())
+            .map(String::from)
+            .collect();
+        if choices.is_empty() {
+            return Glyph::new(default);
+        }
+        Glyph {
+            choices,
+            selected: 0,
+        }
+    }
+
+    pub fn get(&self) -> &str {
+        &self.choices[self.selected]
+    }
+
+    pub fn cycle(&mut self) {
+        self.selected = (self.selected + 1) % self.choices.len();
+    }
+}
+
+// a tab name pattern where `*` matches any run of characters, with an optional color
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabHighlight {
+    pub pattern: String,
+    pub color: Option<PaletteColor>,
+}
+
+impl TabHighlight {
+    fn from_config(value: &str) -> Option<Self> {
+        let (pattern, color) = match value.split_once('=') {
+            Some((pattern, color)) => (pattern.trim(), parse_color(color)),
+            None => (value.trim(), None),
+        };
+        if pattern.is_empty() {
+            return None;
+        }
+        Some(TabHighlight {
+            pattern: pattern.to_string(),
+            color,
+        })
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let mut parts = self.pattern.split('*');
+        let first = parts.next().unwrap_or_default();
+        let Some(mut rest) = name.strip_prefix(first) else {
+            return false;
+        };
+        let mut parts: Vec<&str> = parts.collect();
+        let last = match parts.pop() {
+            Some(last) => last,
+            // no wildcard in the pattern, so the name has to match it exactly
+            None => return rest.is_empty(),
+        };
+        for part in parts {
+            match rest.find(part) {
+                Some(idx) => rest = &rest[idx + part.len()..],
+                None => return false,
+            }
+        }
+        rest.ends_with(last)
+    }
+}
+
+#[derive(Debug)]
+pub struct Config {
+    pub number_style: NumberStyle,
+    pub pin_session_left: bool,
+    // columns reserved for the pinned session segment, 0 means fit the session name
+    pub session_width: usize,
+    pub session_name_position: Side,
+    pub left_more_glyph: Glyph,
+    pub right_more_glyph: Glyph,
+    pub rtl_aware: bool,
+    pub restore_input_mode: bool,
+    // off lands every switch on the target session's own focus, the cache is still written
+    pub restore_focus_on_switch: bool,
+    // how many previously visited tabs the breadcrumb shows, 0 disables it
+    pub breadcrumb_length: usize,
+    pub show_version: bool,
+    // shown instead of the version when set
+    pub brand: Option<String>,
+    pub brand_position: Side,
+    pub page_scroll_on_overflow: bool,
+    // name given to tabs opened by the bar, `{n}` is replaced with the tab number
+    pub new_tab_name: Option<String>,
+    pub rename_new_tab: bool,
+    // the first matching entry decides the accent color of a tab
+    pub highlight_tabs: Vec<TabHighlight>,
+    pub highlight_color: Option<PaletteColor>,
+    // seconds between timer ticks for animated segments
+    pub tick_interval: f64,
+    // name of the cli pipe navigation events are written to
+    pub event_pipe: Option<String>,
+    // draws a separator after every nth tab, 0 disables it
+    pub group_separator_every: usize,
+    pub restore_sync_panes: bool,
+    // rendered while the session has no tabs, `{session}` is replaced with the session name
+    pub empty_placeholder: Option<String>,
+    // fixed colors for tab positions starting from 1
+    pub tab_colors: BTreeMap<usize, PaletteColor>,
+    // by default the active tab keeps the active style even when its position has a color
+    pub tab_colors_on_active: bool,
+    pub debug: bool,
+    // sessions are cycled in this order before the unlisted ones
+    pub session_order: Vec<String>,
+    pub reverse_tab_order: bool,
+    pub hide_when_single_tab: bool,
+    // shows the position of the session among all sessions next to its name
+    pub session_count: bool,
+    pub show_pane_title: bool,
+    pub pane_title_max_width: usize,
+    pub pane_title_position: Side,
+    pub hide_single_tab_number: bool,
+    pub activity_cue: ActivityCue,
+    // splits the whole bar into this many equal slots with one tab each, 0 disables it
+    pub grid_slots: usize,
+    // switches to the session that was focused last time the plugin ran
+    pub restore_last_session: bool,
+    pub card_mode: bool,
+    pub card_separator: Glyph,
+    // reports the tab reached by the cycle_tab pipe and whether it wrapped around
+    pub cycle_tab_feedback: bool,
+    // background of the tab being renamed
+    pub rename_color: Option<PaletteColor>,
+    pub dim_tabs_while_renaming: bool,
+    // modes in which typing doesn't reach the panes, marked on the active tab
+    pub readonly_modes: Vec<InputMode>,
+    pub readonly_glyph: Glyph,
+    // colors inactive tabs by a hash of their name, picking from hash_palette
+    // or from the theme colors when it is empty
+    pub hash_colors: bool,
+    pub hash_colors_on_active: bool,
+    pub hash_palette: Vec<PaletteColor>,
+    // clickable count of the other sessions
+    pub more_sessions: bool,
+    pub more_sessions_glyph: Glyph,
+    pub more_sessions_position: Side,
+    // keeps a window moved with the scroll_window pipe until the active tab changes
+    pub sticky_window: bool,
+    // draws a glyph at the end of every tab that closes it when clicked
+    pub close_buttons: bool,
+    pub close_glyph: Glyph,
+    // while every tab syncs its panes the bar background turns to sync_all_color
+    // instead of each tab being labeled
+    pub sync_all_indicator: bool,
+    pub sync_all_color: Option<PaletteColor>,
+    // on a bar pane of two or more rows the mode and session get a header row
+    // above the tabs, with an optional status row below them
+    pub multi_row: bool,
+    pub status_row: bool,
+    // names shown in place of the real tab names, as `name=alias` pairs, aliases
+    // set with the alias_tab pipe are kept per session and win over these
+    pub tab_aliases: BTreeMap<String, String>,
+    // number of panes across every tab, plugin panes like the bar itself are only
+    // counted with pane_count_plugins
+    pub pane_count: bool,
+    pub pane_count_glyph: Glyph,
+    pub pane_count_plugins: bool,
+    // seconds a layout dump may stay in memory before it is written to the cache
+    // file, 0 writes on every dump
+    pub cache_flush_interval: f64,
+    // marks the bar while the focused tab or pane differs from the last layout
+    // dump, a restore would land elsewhere until the next dump
+    pub dirty_marker: bool,
+    pub dirty_glyph: Glyph,
+    pub dirty_position: Side,
+    pub name_click: NameClick,
+    // shows whether other clients are attached to the session, refreshed on every tick
+    pub shared_indicator: bool,
+    pub shared_glyph: Glyph,
+    pub shared_position: Side,
+    pub scroll_action: ScrollAction,
+    // records where floating panes sit in every layout dump and moves them back
+    // there when the bar of that session starts again, e.g. after a resurrection
+    pub restore_floating_geometry: bool,
+    // a rough measure of output in background tabs since they were last focused,
+    // one glyph of output_levels per level from little to a lot
+    pub output_indicator: bool,
+    pub output_levels: Vec<char>,
+    // every other inactive tab gets a slightly shifted background, true color only
+    // unless the two shades are set
+    pub striped_background: bool,
+    pub stripe_even_bg: Option<ColorSetting>,
+    pub stripe_odd_bg: Option<ColorSetting>,
+    // cells of the bar drawn for a progress set with the set_tab_progress pipe
+    pub progress_width: usize,
+    // colors of the active tab, theme color names or fixed colors, see ColorSetting
+    pub active_tab_fg: Option<ColorSetting>,
+    pub active_tab_bg: Option<ColorSetting>,
+    // longest label a tab gets before it is cut and ends in the ellipsis, 0 for no limit
+    pub max_tab_width: usize,
+    pub ellipsis: String,
+    pub show_session_name: bool,
+    // terminal panes of each tab after its name, the bar and other plugins aren't counted
+    pub show_pane_count: bool,
+    pub invert_scroll: bool,
+    // scrolling past the last tab goes on from the first one and the other way around
+    pub wrap_tabs: bool,
+    // where layout dumps, tab aliases and the last session are kept
+    pub cache_dir: PathBuf,
+    // sessions remembered for the session_back pipe
+    pub session_history_length: usize,
+    // marks background tabs that had output since they were last focused
+    pub activity_indicator: bool,
+    pub activity_glyph: Glyph,
+    pub activity_color: Option<ColorSetting>,
+    // tabs syncing their panes, the active one only takes sync_tab_bg as its text color
+    pub sync_tab_fg: Option<ColorSetting>,
+    pub sync_tab_bg: Option<ColorSetting>,
+    pub sync_suffix: String,
+    pub show_fullscreen_indicator: bool,
+    pub fullscreen_glyph: Glyph,
+    pub show_floating_indicator: bool,
+    pub floating_glyph: Glyph,
+    // a click on the session name opens the session manager
+    pub session_name_click: bool,
+    // the number in front of each tab name, clicks and scrolling don't depend on it
+    pub show_tab_index: bool,
+    // label of each tab with `{index}`, `{name}`, `{pane_count}`, `{sync}`, `{fullscreen}`,
+    // `{floating}` and `{swap_layout}`, without it tabs read `{index} {name}` followed by
+    // their indicators
+    pub tab_format: Option<String>,
+    // keys of the current mode on the right of the bar, as many as the tabs leave room for
+    pub show_mode_hints: bool,
+    // right pushes everything on the bar to its right edge
+    pub align: Side,
+    // a click on an overflow marker pages the window instead of focusing the hidden tab
+    pub page_on_overflow_click: bool,
+    pub click_empty_new_tab: EmptyClick,
+    // a button after the tabs that opens a new tab
+    pub new_tab_button: bool,
+    pub new_tab_glyph: Glyph,
+    pub new_tab_color: Option<ColorSetting>,
+    // dragging a tab and letting go over another moves it there, plugins can't
+    // move tabs so this runs the zellij cli, which has to be on the PATH
+    pub drag_reorder: bool,
+    pub session_name_fg: Option<ColorSetting>,
+    pub session_name_bg: Option<ColorSetting>,
+    // leaves the bar unfilled so a translucent terminal shows through, tabs keep their colors
+    pub transparent_background: bool,
+    // blank cells kept at each end of the bar
+    pub left_padding: usize,
+    pub right_padding: usize,
+    // the number of other clients focused on a tab, after the glyph
+    pub show_other_clients: bool,
+    pub other_clients_glyph: Glyph,
+    pub log_level: LogLevel,
+    // marks tabs with a pane still running the command it was started with
+    pub show_running_marker: bool,
+    pub running_marker: Glyph,
+    // the full mode name instead of its first letter, colored by category
+    pub show_mode_badge: bool,
+    // fg and bg of the badge for normal, locked and every other mode
+    pub mode_badge_colors: [(Option<ColorSetting>, Option<ColorSetting>); 3],
+    pub right_click_action: RightClickAction,
+    // the time at the right edge, refreshed every tick_interval, wasm plugins only
+    // know utc so the offset of the wanted zone is given in hours
+    pub show_clock: bool,
+    pub clock_format: String,
+    pub clock_utc_offset: f64,
+    // off turns every truecolor the bar prints into the closest of 256 colors
+    pub true_color: bool,
+    // `active/total` badge next to the pane count, independent of paging
+    pub show_tab_counter: bool,
+    // shown dimmed on the tab being renamed while its name is empty
+    pub rename_placeholder: Option<String>,
+    // drawn after the name of the tab being renamed
+    pub rename_caret: Option<String>,
+    // dim the active tab while a client_focus pipe says the terminal lost focus
+    pub dim_when_unfocused: bool,
+    // a right click only arms a tab for closing, a second one on it closes it
+    pub confirm_tab_close: bool,
+    // blank cells on either side of a tab name, the active card gets one more
+    pub tab_padding: usize,
+    // drawn between two inactive tabs outside card mode, None draws nothing
+    pub tab_separator: Option<Glyph>,
+    // colors every separator between tabs, they are dimmed text otherwise
+    pub tab_separator_color: Option<ColorSetting>,
+    // scroll events this close to the last step taken are dropped, 0 takes every one
+    pub scroll_debounce_ms: u64,
+    // start with session switching frozen, see the freeze pipe
+    pub frozen: bool,
+    pub cache_format: CacheFormat,
+    // highlight the tab under the pointer, only terminals reporting motion send it
+    pub enable_hover: bool,
+    pub hover_bg: Option<ColorSetting>,
+    // overflowing tabs are windowed so the active one sits nearest the middle of
+    // the bar, a window paged or scrolled by hand takes precedence
+    pub center_active_tab: bool,
+    // the active tab's swap layout name, between the first and the rest of swap_layout_brackets
+    pub show_swap_layout: bool,
+    pub swap_layout_brackets: String,
+    pub click_active_tab_action: ActiveTabClick,
+    // cache the focused pane of every tab, not only the focused tab's
+    pub restore_tab_panes: bool,
+    // on top of the active tab's colors, ansi_term resets them after the tab
+    pub active_tab_bold: bool,
+    pub active_tab_italic: bool,
+    pub active_tab_underline: bool,
+    // tabs whose names share what comes before this are separated from the next group
+    pub group_delimiter: Option<String>,
+    // the separator before a group also names it
+    pub group_labels: bool,
+    // only the active tab and one neighbour on each side, however wide the bar is
+    pub minimal_tabs: bool,
+    // wraps every tab in an osc 8 link to hyperlink_uri, zellij doesn't say whether
+    // the terminal supports them so it is left to the user
+    pub hyperlinks: bool,
+    // `{index}` is the tab's number
+    pub hyperlink_uri: String,
+}
+
+// $XDG_CACHE_HOME/compact-bar or ~/.cache/compact-bar, zellij doesn't always hand
+// its environment to plugins so /tmp is the last resort
+fn default_cache_dir() -> PathBuf {
+    let non_empty = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+    non_empty("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("compact-bar"))
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            number_style: NumberStyle::default(),
+            pin_session_left: false,
+            session_width: 0,
+            session_name_position: Side::default(),
+            left_more_glyph: Glyph::new("←"),
+            right_more_glyph: Glyph::new("→"),
+            rtl_aware: false,
+            restore_input_mode: false,
+            restore_focus_on_switch: true,
+            breadcrumb_length: 0,
+            show_version: false,
+            brand: None,
+            brand_position: Side::default(),
+            page_scroll_on_overflow: false,
+            new_tab_name: None,
+            rename_new_tab: false,
+            highlight_tabs: vec![],
+            highlight_color: None,
+            tick_interval: 1.0,
+            event_pipe: None,
+            group_separator_every: 0,
+            restore_sync_panes: false,
+            empty_placeholder: None,
+            tab_colors: BTreeMap::new(),
+            tab_colors_on_active: false,
+            debug: false,
+            session_order: vec![],
+            reverse_tab_order: false,
+            hide_when_single_tab: false,
+            session_count: false,
+            show_pane_title: false,
+            pane_title_max_width: 20,
+            pane_title_position: Side::default(),
+            hide_single_tab_number: false,
+            activity_cue: ActivityCue::default(),
+            grid_slots: 0,
+            restore_last_session: false,
+            card_mode: false,
+            card_separator: Glyph::new("│"),
+            cycle_tab_feedback: false,
+            rename_color: None,
+            dim_tabs_while_renaming: false,
+            readonly_modes: vec![],
+            readonly_glyph: Glyph::new("RO"),
+            hash_colors: false,
+            hash_colors_on_active: false,
+            hash_palette: vec![],
+            more_sessions: false,
+            more_sessions_glyph: Glyph::new("⋯"),
+            more_sessions_position: Side::default(),
+            sticky_window: false,
+            close_buttons: false,
+            close_glyph: Glyph::new("×"),
+            sync_all_indicator: false,
+            sync_all_color: None,
+            multi_row: false,
+            status_row: false,
+            tab_aliases: BTreeMap::new(),
+            pane_count: false,
+            pane_count_glyph: Glyph::new("▦"),
+            pane_count_plugins: false,
+            cache_flush_interval: 0.0,
+            dirty_marker: false,
+            dirty_glyph: Glyph::new("●"),
+            dirty_position: Side::default(),
+            name_click: NameClick::default(),
+            shared_indicator: false,
+            shared_glyph: Glyph::new("⇄"),
+            shared_position: Side::default(),
+            scroll_action: ScrollAction::default(),
+            restore_floating_geometry: false,
+            output_indicator: false,
+            output_levels: vec!['▁', '▄', '█'],
+            striped_background: false,
+            stripe_even_bg: None,
+            stripe_odd_bg: None,
+            progress_width: 3,
+            active_tab_fg: None,
+            active_tab_bg: None,
+            max_tab_width: 20,
+            ellipsis: String::from("…"),
+            show_session_name: true,
+            show_pane_count: false,
+            invert_scroll: false,
+            wrap_tabs: false,
+            cache_dir: default_cache_dir(),
+            session_history_length: 20,
+            activity_indicator: false,
+            activity_glyph: Glyph::new("●"),
+            activity_color: None,
+            sync_tab_fg: None,
+            sync_tab_bg: None,
+            sync_suffix: String::from(" (Sync)"),
+            show_fullscreen_indicator: false,
+            fullscreen_glyph: Glyph::new("⛶"),
+            show_floating_indicator: false,
+            floating_glyph: Glyph::new("◳"),
+            session_name_click: false,
+            show_tab_index: true,
+            tab_format: None,
+            show_mode_hints: false,
+            align: Side::Left,
+            page_on_overflow_click: false,
+            click_empty_new_tab: EmptyClick::default(),
+            new_tab_button: false,
+            new_tab_glyph: Glyph::new("+"),
+            new_tab_color: None,
+            drag_reorder: false,
+            session_name_fg: None,
+            session_name_bg: None,
+            transparent_background: false,
+            left_padding: 0,
+            right_padding: 0,
+            show_other_clients: false,
+            other_clients_glyph: Glyph::new("👥"),
+            log_level: LogLevel::default(),
+            show_running_marker: false,
+            running_marker: Glyph::new("*"),
+            show_mode_badge: false,
+            mode_badge_colors: [(None, None); 3],
+            right_click_action: RightClickAction::default(),
+            show_clock: false,
+            clock_format: String::from("%H:%M"),
+            clock_utc_offset: 0.0,
+            true_color: true,
+            show_tab_counter: false,
+            rename_placeholder: None,
+            rename_caret: None,
+            dim_when_unfocused: false,
+            confirm_tab_close: false,
+            tab_padding: 1,
+            tab_separator: None,
+            tab_separator_color: None,
+            scroll_debounce_ms: 0,
+            frozen: false,
+            cache_format: CacheFormat::default(),
+            enable_hover: false,
+            hover_bg: None,
+            center_active_tab: false,
+            show_swap_layout: false,
+            swap_layout_brackets: String::from("{}"),
+            click_active_tab_action: ActiveTabClick::default(),
+            restore_tab_panes: false,
+            active_tab_bold: false,
+            active_tab_italic: false,
+            active_tab_underline: false,
+            group_delimiter: None,
+            group_labels: false,
+            minimal_tabs: false,
+            hyperlinks: false,
+            hyperlink_uri: String::from("zellij-tab:{index}"),
+        }
+    }
+}
+
+impl Config {
+    // density only picks the defaults, every key below still overrides them
+    //
+    //                        compact   verbose
+    //   tab_padding          1         2
+    //   show_session_name    on        on, pin_session_left off
+    //   pane_count           off       on
+    //   show_tab_counter     off       on
+    //   fullscreen, floating,
+    //   other clients and
+    //   running indicators   off       on
+    fn preset(density: Density) -> Self {
+        match density {
+            Density::Compact => Config::default(),
+            Density::Verbose => Config {
+                tab_padding: 2,
+                show_session_name: true,
+                pin_session_left: false,
+                pane_count: true,
+                show_tab_counter: true,
+                show_fullscreen_indicator: true,
+                show_floating_indicator: true,
+                show_other_clients: true,
+                show_running_marker: true,
+                ..Config::default()
+            },
+        }
+    }
+
+    pub fn from_configuration(configuration: &BTreeMap<String, String>) -> Self {
+        let density = configuration
+            .get("density")
+            .map_or(Density::default(), |value| Density::from_config(value));
+        let mut config = Config::preset(density);
+        if let Some(value) = configuration.get("number_style") {
+            config.number_style = NumberStyle::from_config(value);
+        }
+        if let Some(value) = configuration.get("pin_session_left") {
+            config.pin_session_left = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("session_width") {
+            config.session_width = value.trim().parse().unwrap_or(0);
+        }
+        if let Some(value) = configuration.get("left_more_glyph") {
+            config.left_more_glyph = Glyph::from_config(value, "←");
+        }
+        if let Some(value) = configuration.get("right_more_glyph") {
+            config.right_more_glyph = Glyph::from_config(value, "→");
+        }
+        if let Some(value) = configuration.get("rtl_aware") {
+            config.rtl_aware = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("restore_input_mode") {
+            config.restore_input_mode = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("restore_focus_on_switch") {
+            config.restore_focus_on_switch = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("breadcrumb_length") {
+            config.breadcrumb_length = value.trim().parse().unwrap_or(0);
+        }
+        if let Some(value) = configuration.get("show_version") {
+            config.show_version = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("brand") {
+            config.brand = Some(value.clone()).filter(|brand| !brand.is_empty());
+        }
+        if let Some(value) = configuration.get("brand_position") {
+            config.brand_position = Side::from_config(value);
+        }
+        if let Some(value) = configuration.get("page_scroll_on_overflow") {
+            config.page_scroll_on_overflow = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("new_tab_name") {
+            config.new_tab_name = Some(value.clone()).filter(|name| !name.is_empty());
+        }
+        if let Some(value) = configuration.get("rename_new_tab") {
+            config.rename_new_tab = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("highlight_tabs") {
+            config.highlight_tabs = value
+                .split(',')
+                .filter_map(TabHighlight::from_config)
+                .collect();
+        }
+        if let Some(value) = configuration.get("highlight_color") {
+            config.highlight_color = parse_color(value);
+        }
+        if let Some(value) = configuration.get("tick_interval") {
+            config.tick_interval = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|interval: &f64| *interval > 0.0)
+                .unwrap_or(1.0);
+        }
+        if let Some(value) = configuration.get("event_pipe") {
+            config.event_pipe = Some(value.trim().to_string()).filter(|name| !name.is_empty());
+        }
+        if let Some(value) = configuration.get("group_separator_every") {
+            config.group_separator_every = value.trim().parse().unwrap_or(0);
+        }
+        if let Some(value) = configuration.get("restore_sync_panes") {
+            config.restore_sync_panes = parse_bool(value);
+        }
+        // empty_bar_text is the same setting under another name
+        if let Some(value) = configuration
+            .get("empty_placeholder")
+            .or_else(|| configuration.get("empty_bar_text"))
+        {
+            config.empty_placeholder = Some(value.clone()).filter(|text| !text.is_empty());
+        }
+        if let Some(value) = configuration.get("tab_colors") {
+            config.tab_colors = value
+                .split(',')
+                .filter_map(|entry| {
+                    let (position, color) = entry.split_once('=')?;
+                    Some((position.trim().parse().ok()?, parse_color(color)?))
+                })
+                .collect();
+        }
+        if let Some(value) = configuration.get("tab_colors_on_active") {
+            config.tab_colors_on_active = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("debug") {
+            config.debug = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("session_order") {
+            config.session_order = value
+                .split(',')
+                .map(|name| name.trim())
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        if let Some(value) = configuration.get("reverse_tab_order") {
+            config.reverse_tab_order = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("hide_when_single_tab") {
+            config.hide_when_single_tab = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("session_count") {
+            config.session_count = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("show_pane_title") {
+            config.show_pane_title = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("pane_title_max_width") {
+            config.pane_title_max_width = value.trim().parse().unwrap_or(20);
+        }
+        if let Some(value) = configuration.get("pane_title_position") {
+            config.pane_title_position = Side::from_config(value);
+        }
+        if let Some(value) = configuration.get("hide_single_tab_number") {
+            config.hide_single_tab_number = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("activity_cue") {
+            config.activity_cue = ActivityCue::from_config(value);
+        }
+        if let Some(value) = configuration.get("grid_slots") {
+            config.grid_slots = value.trim().parse().unwrap_or(0);
+        }
+        if let Some(value) = configuration.get("restore_last_session") {
+            config.restore_last_session = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("card_mode") {
+            config.card_mode = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("card_separator") {
+            config.card_separator = Glyph::from_config(value, "│");
+        }
+        if let Some(value) = configuration.get("cycle_tab_feedback") {
+            config.cycle_tab_feedback = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("rename_color") {
+            config.rename_color = parse_color(value);
+        }
+        if let Some(value) = configuration.get("dim_tabs_while_renaming") {
+            config.dim_tabs_while_renaming = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("readonly_modes") {
+            config.readonly_modes = value
+                .split(',')
+                .filter_map(|mode| mode.trim().parse().ok())
+                .collect();
+        }
+        if let Some(value) = configuration.get("readonly_glyph") {
+            config.readonly_glyph = Glyph::from_config(value, "RO");
+        }
+        // color_tabs_by_name is the same setting under another name
+        if let Some(value) = configuration
+            .get("hash_colors")
+            .or_else(|| configuration.get("color_tabs_by_name"))
+        {
+            config.hash_colors = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("hash_colors_on_active") {
+            config.hash_colors_on_active = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("hash_palette") {
+            config.hash_palette = value.split(',').filter_map(parse_color).collect();
+        }
+        if let Some(value) = configuration.get("more_sessions") {
+            config.more_sessions = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("more_sessions_glyph") {
+            config.more_sessions_glyph = Glyph::from_config(value, "⋯");
+        }
+        if let Some(value) = configuration.get("session_name_position") {
+            config.session_name_position = Side::from_config(value);
+        }
+        if let Some(value) = configuration.get("more_sessions_position") {
+            config.more_sessions_position = Side::from_config(value);
+        }
+        if let Some(value) = configuration.get("sticky_window") {
+            config.sticky_window = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("close_buttons") {
+            config.close_buttons = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("close_glyph") {
+            config.close_glyph = Glyph::from_config(value, "×");
+        }
+        if let Some(value) = configuration.get("sync_all_indicator") {
+            config.sync_all_indicator = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("sync_all_color") {
+            config.sync_all_color = parse_color(value);
+        }
+        if let Some(value) = configuration.get("multi_row") {
+            config.multi_row = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("status_row") {
+            config.status_row = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("tab_aliases") {
+            config.tab_aliases = value
+                .split(',')
+                .filter_map(|entry| {
+                    let (name, alias) = entry.split_once('=')?;
+                    Some((name.trim().to_string(), alias.trim().to_string()))
+                })
+                .filter(|(name, alias)| !name.is_empty() && !alias.is_empty())
+                .collect();
+        }
+        if let Some(value) = configuration.get("pane_count") {
+            config.pane_count = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("pane_count_glyph") {
+            config.pane_count_glyph = Glyph::from_config(value, "▦");
+        }
+        if let Some(value) = configuration.get("pane_count_plugins") {
+            config.pane_count_plugins = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("cache_flush_interval") {
+            config.cache_flush_interval = value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|interval: &f64| *interval >= 0.0)
+                .unwrap_or(0.0);
+        }
+        if let Some(value) = configuration.get("dirty_marker") {
+            config.dirty_marker = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("dirty_glyph") {
+            config.dirty_glyph = Glyph::from_config(value, "●");
+        }
+        if let Some(value) = configuration.get("dirty_position") {
+            config.dirty_position = Side::from_config(value);
+        }
+        if let Some(value) = configuration.get("name_click") {
+            config.name_click = NameClick::from_config(value);
+        }
+        if let Some(value) = configuration.get("shared_indicator") {
+            config.shared_indicator = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("shared_glyph") {
+            config.shared_glyph = Glyph::from_config(value, "⇄");
+        }
+        if let Some(value) = configuration.get("shared_position") {
+            config.shared_position = Side::from_config(value);
+        }
+        if let Some(value) = configuration.get("scroll_action") {
+            config.scroll_action = ScrollAction::from_config(value);
+        }
+        if let Some(value) = configuration.get("restore_floating_geometry") {
+            config.restore_floating_geometry = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("output_indicator") {
+            config.output_indicator = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("output_levels") {
+            let levels: Vec<char> = value.trim().chars().collect();
+            if !levels.is_empty() {
+                config.output_levels = levels;
+            }
+        }
+        if let Some(value) = configuration.get("striped_background") {
+            config.striped_background = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("stripe_even_bg") {
+            config.stripe_even_bg = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("stripe_odd_bg") {
+            config.stripe_odd_bg = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("progress_width") {
+            config.progress_width = value.trim().parse().unwrap_or(3);
+        }
+        if let Some(value) = configuration.get("active_tab_fg") {
+            config.active_tab_fg = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("active_tab_bg") {
+            config.active_tab_bg = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("max_tab_width") {
+            config.max_tab_width = value.trim().parse().unwrap_or(20);
+        }
+        if let Some(value) = configuration.get("ellipsis") {
+            config.ellipsis = value.to_string();
+        }
+        if let Some(value) = configuration.get("show_session_name") {
+            config.show_session_name = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("show_pane_count") {
+            config.show_pane_count = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("invert_scroll") {
+            config.invert_scroll = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("wrap_tabs") {
+            config.wrap_tabs = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("cache_dir") {
+            let value = value.trim();
+            if !value.is_empty() {
+                config.cache_dir = PathBuf::from(value);
+            }
+        }
+        if let Some(value) = configuration.get("session_history_length") {
+            config.session_history_length = value.trim().parse().unwrap_or(20);
+        }
+        if let Some(value) = configuration.get("activity_indicator") {
+            config.activity_indicator = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("activity_glyph") {
+            config.activity_glyph = Glyph::from_config(value, "●");
+        }
+        if let Some(value) = configuration.get("activity_color") {
+            config.activity_color = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("sync_tab_fg") {
+            config.sync_tab_fg = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("sync_tab_bg") {
+            config.sync_tab_bg = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("sync_suffix") {
+            config.sync_suffix = value.to_string();
+        }
+        if let Some(value) = configuration.get("show_fullscreen_indicator") {
+            config.show_fullscreen_indicator = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("fullscreen_glyph") {
+            config.fullscreen_glyph = Glyph::from_config(value, "⛶");
+        }
+        if let Some(value) = configuration.get("show_floating_indicator") {
+            config.show_floating_indicator = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("floating_glyph") {
+            config.floating_glyph = Glyph::from_config(value, "◳");
+        }
+        if let Some(value) = configuration.get("session_name_click") {
+            config.session_name_click = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("show_tab_index") {
+            config.show_tab_index = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("tab_format") {
+            config.tab_format = Some(value.clone()).filter(|format| !format.is_empty());
+        }
+        if let Some(value) = configuration.get("show_mode_hints") {
+            config.show_mode_hints = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("align") {
+            // unlike the segment positions the bar starts on the left unless told otherwise
+            config.align = match value.trim().to_lowercase().as_str() {
+                "right" => Side::Right,
+                _ => Side::Left,
+            };
+        }
+        if let Some(value) = configuration.get("page_on_overflow_click") {
+            config.page_on_overflow_click = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("click_empty_new_tab") {
+            config.click_empty_new_tab = EmptyClick::from_config(value);
+        }
+        if let Some(value) = configuration.get("new_tab_button") {
+            config.new_tab_button = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("new_tab_glyph") {
+            config.new_tab_glyph = Glyph::from_config(value, "+");
+        }
+        if let Some(value) = configuration.get("new_tab_color") {
+            config.new_tab_color = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("drag_reorder") {
+            config.drag_reorder = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("session_name_fg") {
+            config.session_name_fg = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("session_name_bg") {
+            config.session_name_bg = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("transparent_background") {
+            config.transparent_background = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("left_padding") {
+            config.left_padding = value.trim().parse().unwrap_or(0);
+        }
+        if let Some(value) = configuration.get("right_padding") {
+            config.right_padding = value.trim().parse().unwrap_or(0);
+        }
+        if let Some(value) = configuration.get("show_other_clients") {
+            config.show_other_clients = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("other_clients_glyph") {
+            config.other_clients_glyph = Glyph::from_config(value, "👥");
+        }
+        if let Some(value) = configuration.get("log_level") {
+            config.log_level = LogLevel::from_config(value);
+        }
+        if let Some(value) = configuration.get("show_running_marker") {
+            config.show_running_marker = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("running_marker") {
+            config.running_marker = Glyph::from_config(value, "*");
+        }
+        if let Some(value) = configuration.get("show_mode_badge") {
+            config.show_mode_badge = parse_bool(value);
+        }
+        for (category, name) in ["normal", "locked", "other"].iter().enumerate() {
+            if let Some(value) = configuration.get(&format!("mode_badge_{}_fg", name)) {
+                config.mode_badge_colors[category].0 = ColorSetting::from_config(value);
+            }
+            if let Some(value) = configuration.get(&format!("mode_badge_{}_bg", name)) {
+                config.mode_badge_colors[category].1 = ColorSetting::from_config(value);
+            }
+        }
+        if let Some(value) = configuration.get("right_click_action") {
+            config.right_click_action = RightClickAction::from_config(value);
+        }
+        if let Some(value) = configuration.get("show_clock") {
+            config.show_clock = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("clock_format") {
+            config.clock_format = value.clone();
+        }
+        if let Some(value) = configuration.get("clock_utc_offset") {
+            config.clock_utc_offset = value.trim().parse().unwrap_or(0.0);
+        }
+        if let Some(value) = configuration.get("true_color") {
+            config.true_color = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("show_tab_counter") {
+            config.show_tab_counter = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("rename_placeholder") {
+            config.rename_placeholder = Some(value.to_string());
+        }
+        if let Some(value) = configuration.get("rename_caret") {
+            config.rename_caret = Some(value.to_string()).filter(|caret| !caret.is_empty());
+        }
+        if let Some(value) = configuration.get("dim_when_unfocused") {
+            config.dim_when_unfocused = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("confirm_tab_close") {
+            config.confirm_tab_close = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("tab_padding") {
+            config.tab_padding = value.trim().parse().unwrap_or(1);
+        }
+        if let Some(value) = configuration.get("tab_separator") {
+            config.tab_separator = Some(Glyph::from_config(value, "│"));
+        }
+        if let Some(value) = configuration.get("tab_separator_color") {
+            config.tab_separator_color = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("scroll_debounce_ms") {
+            config.scroll_debounce_ms = value.trim().parse().unwrap_or(0);
+        }
+        if let Some(value) = configuration.get("frozen") {
+            config.frozen = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("cache_format") {
+            config.cache_format = CacheFormat::from_config(value);
+        }
+        if let Some(value) = configuration.get("enable_hover") {
+            config.enable_hover = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("hover_bg") {
+            config.hover_bg = ColorSetting::from_config(value);
+        }
+        if let Some(value) = configuration.get("center_active_tab") {
+            config.center_active_tab = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("show_swap_layout") {
+            config.show_swap_layout = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("swap_layout_brackets") {
+            config.swap_layout_brackets = value.trim().to_string();
+        }
+        if let Some(value) = configuration.get("click_active_tab_action") {
+            config.click_active_tab_action = ActiveTabClick::from_config(value);
+        }
+        if let Some(value) = configuration.get("restore_tab_panes") {
+            config.restore_tab_panes = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("active_tab_bold") {
+            config.active_tab_bold = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("active_tab_italic") {
+            config.active_tab_italic = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("active_tab_underline") {
+            config.active_tab_underline = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("group_delimiter") {
+            config.group_delimiter =
+                Some(value.to_string()).filter(|delimiter| !delimiter.is_empty());
+        }
+        if let Some(value) = configuration.get("group_labels") {
+            config.group_labels = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("minimal_tabs") {
+            config.minimal_tabs = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("hyperlinks") {
+            config.hyperlinks = parse_bool(value);
+        }
+        if let Some(value) = configuration.get("hyperlink_uri") {
+            config.hyperlink_uri = value.trim().to_string();
+        }
+        config
+    }
+
+    pub fn tab_color(&self, position: usize, active: bool) -> Option<PaletteColor> {
+        if active && !self.tab_colors_on_active {
+            return None;
+        }
+        self.tab_colors.get(&position).copied()
+    }
+
+    // true when any enabled segment changes over time
+    pub fn needs_timer(&self) -> bool {
+        self.activity_cue == ActivityCue::Flash
+            || self.cache_flush_interval > 0.0
+            || self.shared_indicator
+            || self.show_clock
+            || self.confirm_tab_close
+    }
+
+    // a window moved by hand is kept until the active tab changes
+    pub fn keeps_window(&self) -> bool {
+        self.sticky_window
+            || self.scroll_action == ScrollAction::PageWindow
+            || self.page_on_overflow_click
+    }
+
+    // the focused pane's title is drawn on the tab row or the status row
+    pub fn draws_pane_title(&self) -> bool {
+        self.show_pane_title || (self.multi_row && self.status_row)
+    }
+
+    // segments drawn from the pane manifest
+    pub fn renders_on_pane_update(&self) -> bool {
+        self.draws_pane_title()
+            || self.pane_count
+            || self.dirty_marker
+            || self.show_pane_count
+            || self.show_running_marker
+    }
+
+    pub fn tab_highlight(&self, name: &str, default: PaletteColor) -> Option<PaletteColor> {
+        self.highlight_tabs
+            .iter()
+            .find(|highlight| highlight.matches(name))
+            .map(|highlight| highlight.color.or(self.highlight_color).unwrap_or(default))
+    }
+
+    pub fn cycle_glyphs(&mut self) {
+        self.left_more_glyph.cycle();
+        self.right_more_glyph.cycle();
+        self.card_separator.cycle();
+        self.readonly_glyph.cycle();
+        self.more_sessions_glyph.cycle();
+        self.close_glyph.cycle();
+        self.pane_count_glyph.cycle();
+        self.dirty_glyph.cycle();
+        self.shared_glyph.cycle();
+        self.activity_glyph.cycle();
+        self.fullscreen_glyph.cycle();
+        self.floating_glyph.cycle();
+        self.new_tab_glyph.cycle();
+        self.other_clients_glyph.cycle();
+        self.running_marker.cycle();
+        if let Some(separator) = &mut self.tab_separator {
+            separator.cycle();
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "true" | "yes" | "on" | "1"
+    )
+}
+
+// accepts `#rrggbb` hex colors or 256 color palette indices
+fn parse_color(value: &str) -> Option<PaletteColor> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let r = u8::from_str_radix(hex.get(0..2)?, 16).ok()?;
+        let g = u8::from_str_radix(hex.get(2..4)?, 16).ok()?;
+        let b = u8::from_str_radix(hex.get(4..6)?, 16).ok()?;
+        return Some(PaletteColor::Rgb((r, g, b)));
+    }
+    value.parse().ok().map(PaletteColor::EightBit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_width::UnicodeWidthStr;
+
+    fn config(pairs: &[(&str, &str)]) -> Config {
+        let configuration: BTreeMap<String, String> = pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Config::from_configuration(&configuration)
+    }
+
+    #[test]
+    fn number_styles_map_every_digit() {
+        assert_eq!(NumberStyle::Normal.format(120), "120");
+        assert_eq!(NumberStyle::Superscript.format(120), "¹²⁰");
+        assert_eq!(NumberStyle::Subscript.format(1234567890), "₁₂₃₄₅₆₇₈₉₀");
+    }
+
+    #[test]
+    fn styled_numbers_are_one_column_per_digit() {
+        for style in [NumberStyle::Superscript, NumberStyle::Subscript] {
+            assert_eq!(style.format(7).width(), 1);
+            assert_eq!(style.format(42).width(), 2);
+            assert_eq!(style.format(1234567890).width(), 10);
+        }
+    }
+
+    #[test]
+    fn number_style_falls_back_to_digits() {
+        assert_eq!(config(&[]).number_style, NumberStyle::Normal);
+        assert_eq!(
+            config(&[("number_style", "Superscript")]).number_style,
+            NumberStyle::Superscript
+        );
+        assert_eq!(
+            config(&[("number_style", "roman")]).number_style,
+            NumberStyle::Normal
+        );
+    }
+
+    #[test]
+    fn glyph_fallbacks_are_parsed_in_order() {
+        let glyph = Glyph::from_config(" ‹ ,<, ,«", "←");
+        assert_eq!(glyph.choices, vec!["‹", "<", "«"]);
+        assert_eq!(glyph.get(), "‹");
+        assert_eq!(Glyph::from_config(" , ", "←").get(), "←");
+    }
+
+    #[test]
+    fn cycling_glyphs_walks_the_fallbacks_and_wraps() {
+        let mut config = config(&[("left_more_glyph", "‹,<"), ("right_more_glyph", "›")]);
+        config.cycle_glyphs();
+        assert_eq!(config.left_more_glyph.get(), "<");
+        assert_eq!(config.right_more_glyph.get(), "›");
+        config.cycle_glyphs();
+        assert_eq!(config.left_more_glyph.get(), "‹");
+    }
+
+    #[test]
+    fn matching_tabs_get_the_highlight() {
+        let config = config(&[
+            ("highlight_tabs", "mon*=#ff0000, *build*, logs"),
+            ("highlight_color", "33"),
+        ]);
+        let default = PaletteColor::EightBit(5);
+        assert_eq!(
+            config.tab_highlight("monitoring", default),
+            Some(PaletteColor::Rgb((255, 0, 0)))
+        );
+        // without its own color an entry takes highlight_color
+        assert_eq!(
+            config.tab_highlight("nightly build 2", default),
+            Some(PaletteColor::EightBit(33))
+        );
+        assert_eq!(
+            config.tab_highlight("logs", default),
+            Some(PaletteColor::EightBit(33))
+        );
+    }
+
+    #[test]
+    fn other_tabs_are_not_highlighted() {
+        let config = config(&[("highlight_tabs", "mon*, logs")]);
+        let default = PaletteColor::EightBit(5);
+        assert_eq!(config.tab_highlight("editor", default), None);
+        assert_eq!(config.tab_highlight("my monitor", default), None);
+        // a pattern without a wildcard has to match the whole name
+        assert_eq!(config.tab_highlight("logs 2", default), None);
+        assert_eq!(config.tab_highlight("monitor", default), Some(default));
+    }
+
+    #[test]
+    fn fixed_colors_apply_to_configured_positions() {
+        let config = config(&[("tab_colors", "1=#00ff00, 3=196, x=12, 4=nope")]);
+        assert_eq!(
+            config.tab_color(1, false),
+            Some(PaletteColor::Rgb((0, 255, 0)))
+        );
+        assert_eq!(
+            config.tab_color(3, false),
+            Some(PaletteColor::EightBit(196))
+        );
+        // unconfigured and malformed positions keep the usual colors
+        assert_eq!(config.tab_color(2, false), None);
+        assert_eq!(config.tab_color(4, false), None);
+    }
+
+    #[test]
+    fn fixed_colors_skip_the_active_tab_unless_asked() {
+        let color = Some(PaletteColor::EightBit(196));
+        assert_eq!(config(&[("tab_colors", "3=196")]).tab_color(3, true), None);
+        let config = config(&[("tab_colors", "3=196"), ("tab_colors_on_active", "true")]);
+        assert_eq!(config.tab_color(3, true), color);
+    }
+
+    #[test]
+    fn readonly_modes_skip_unknown_names() {
+        let config = config(&[("readonly_modes", "Scroll, nope, Search")]);
+        assert_eq!(
+            config.readonly_modes,
+            vec![InputMode::Scroll, InputMode::Search]
+        );
+    }
+    #[test]
+    fn the_status_row_redraws_on_pane_updates() {
+        assert!(!config(&[]).renders_on_pane_update());
+        assert!(config(&[("show_pane_title", "true")]).renders_on_pane_update());
+        assert!(config(&[("multi_row", "true"), ("status_row", "true")]).renders_on_pane_update());
+        assert!(!config(&[("status_row", "true")]).renders_on_pane_update());
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+
+use crate::{ClientLayout, FloatingGeometry};
+
+// the layout cache as kdl, one node per client:
+//
+//   version 4
+//   client 1234 {
+//       saved_at 1700000000
+//       tab 0
+//       pane 7 plugin=false floating=false
+//       mode "Normal"
+//       synced_tabs 0 2
+//       window_start 2
+//       tab_pane 1 3 plugin=false
+//       floating 7 plugin=false x=10 y=5 width=80 height=20
+//       switched_at 1700000100
+//   }
+pub(crate) fn layout_to_kdl(version: u32, clients: &BTreeMap<u32, ClientLayout>) -> String {
+    let mut kdl = format!("version {}\n", version);
+    for (pid, layout) in clients {
+        kdl.push_str(&format!("client {} {{\n", pid));
+        kdl.push_str(&format!("    saved_at {}\n", layout.saved_at));
+        kdl.push_str(&format!("    tab {}\n", layout.tab_idx));
+        kdl.push_str(&format!(
+            "    pane {} plugin={} floating={}\n",
+            layout.pane.0, layout.pane.1, layout.pane_floating
+        ));
+        // the reader splits nodes on whitespace and doesn't unescape, a mode that
+        // couldn't be read back is left out
+        if let Some(mode) = layout.mode.as_ref().filter(|mode| is_plain_string(mode)) {
+            kdl.push_str(&format!("    mode \"{}\"\n", mode));
+        }
+        if !layout.synced_tabs.is_empty() {
+            let tabs: Vec<String> = layout
+                .synced_tabs
+                .iter()
+                .map(|tab| tab.to_string())
+                .collect();
+            kdl.push_str(&format!("    synced_tabs {}\n", tabs.join(" ")));
+        }
+        if let Some(start) = layout.window_start {
+            kdl.push_str(&format!("    window_start {}\n", start));
+        }
+        for (position, (id, is_plugin)) in &layout.tab_panes {
+            kdl.push_str(&format!(
+                "    tab_pane {} {} plugin={}\n",
+                position, id, is_plugin
+            ));
+        }
+        for geometry in &layout.floating {
+            kdl.push_str(&format!(
+                "    floating {} plugin={} x={} y={} width={} height={}\n",
+                geometry.pane.0,
+                geometry.pane.1,
+                geometry.x,
+                geometry.y,
+                geometry.width,
+                geometry.height
+            ));
+        }
+        if let Some(switched_at) = layout.switched_at {
+            kdl.push_str(&format!("    switched_at {}\n", switched_at));
+        }
+        kdl.push_str("}\n");
+    }
+    kdl
+}
+
+fn is_plain_string(value: &str) -> bool {
+    !value.is_empty() && !value.contains(|c: char| c == '"' || c == '\\' || c.is_whitespace())
+}
+
+// reads back what layout_to_kdl wrote, the version and the clients, nodes it
+// doesn't know are skipped and anything malformed gives None
+pub(crate) fn layout_from_kdl(kdl: &str) -> Option<(u32, BTreeMap<u32, ClientLayout>)> {
+    let mut version = None;
+    let mut clients = BTreeMap::new();
+    let mut current: Option<(u32, ClientLayout)> = None;
+    for line in kdl.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let (name, args, props) = parse_node(line);
+        if name == "}" {
+            let (pid, layout) = current.take()?;
+            clients.insert(pid, layout);
+            continue;
+        }
+        let number = |i: usize| args.get(i).and_then(|arg| arg.parse::<usize>().ok());
+        let flag = |key: &str| props.get(key) == Some(&"true");
+        let prop = |key: &str| props.get(key).and_then(|value| value.parse::<usize>().ok());
+        match (name, current.as_mut()) {
+            ("version", None) => version = Some(args.first()?.parse().ok()?),
+            ("client", None) => {
+                let pid = args.first()?.parse().ok()?;
+                current = Some((pid, ClientLayout::default()));
+            }
+            // a client block left open
+            ("client", Some(_)) => return None,
+            ("saved_at", Some((_, layout))) => layout.saved_at = args.first()?.parse().ok()?,
+            ("tab", Some((_, layout))) => layout.tab_idx = number(0)?,
+            ("pane", Some((_, layout))) => {
+                layout.pane = (args.first()?.parse().ok()?, flag("plugin"));
+                layout.pane_floating = flag("floating");
+            }
+            ("mode", Some((_, layout))) => {
+                let mode = args.first()?.strip_prefix('"')?.strip_suffix('"')?;
+                if !is_plain_string(mode) {
+                    return None;
+                }
+                layout.mode = Some(mode.to_string());
+            }
+            ("synced_tabs", Some((_, layout))) => {
+                layout.synced_tabs = (0..args.len()).map(number).collect::<Option<_>>()?;
+            }
+            ("window_start", Some((_, layout))) => layout.window_start = Some(number(0)?),
+            ("tab_pane", Some((_, layout))) => {
+                let id = args.get(1)?.parse().ok()?;
+                layout.tab_panes.insert(number(0)?, (id, flag("plugin")));
+            }
+            ("floating", Some((_, layout))) => layout.floating.push(FloatingGeometry {
+                pane: (args.first()?.parse().ok()?, flag("plugin")),
+                x: prop("x")?,
+                y: prop("y")?,
+                width: prop("width")?,
+                height: prop("height")?,
+            }),
+            ("switched_at", Some((_, layout))) => {
+                layout.switched_at = Some(args.first()?.parse().ok()?);
+            }
+            _ => {}
+        }
+    }
+    if current.is_some() {
+        return None;
+    }
+    Some((version?, clients))
+}
+
+// the node name, its arguments and its `key=value` properties, a trailing `{`
+// opening the children is dropped
+fn parse_node(line: &str) -> (&str, Vec<&str>, BTreeMap<&str, &str>) {
+    let mut tokens = line.split_whitespace().filter(|token| *token != "{");
+    let name = tokens.next().unwrap_or_default();
+    let mut args = vec![];
+    let mut props = BTreeMap::new();
+    for token in tokens {
+        match token.split_once('=') {
+            Some((key, value)) => {
+                props.insert(key, value);
+            }
+            None => args.push(token),
+        }
+    }
+    (name, args, props)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn every_field() -> ClientLayout {
+        ClientLayout {
+            tab_idx: 1,
+            pane: (7, false),
+            pane_floating: true,
+            mode: Some(String::from("Locked")),
+            synced_tabs: vec![0, 2],
+            floating: vec![FloatingGeometry {
+                pane: (7, false),
+                x: 10,
+                y: 5,
+                width: 80,
+                height: 20,
+            }],
+            window_start: Some(2),
+            tab_panes: BTreeMap::from([(0, (3, false)), (1, (7, true))]),
+            saved_at: 1_700_000_000,
+            switched_at: Some(1_700_000_100),
+        }
+    }
+
+    #[test]
+    fn every_field_round_trips() {
+        let clients = BTreeMap::from([(12, every_field()), (13, ClientLayout::default())]);
+        let kdl = layout_to_kdl(4, &clients);
+        assert_eq!(layout_from_kdl(&kdl), Some((4, clients)));
+    }
+
+    #[test]
+    fn malformed_input_reads_as_none() {
+        for kdl in [
+            "",
+            "client 12 {\n    tab 1\n}\n",
+            "version 4\nclient 12 {\n    tab 1\n",
+            "version 4\nclient 12 {\n    tab 1\nclient 13 {\n}\n",
+            "version 4\n}\n",
+            "version five\n",
+            "version 4\nclient 12 {\n    tab one\n}\n",
+            "version 4\nclient 12 {\n    floating 7 plugin=false x=10\n}\n",
+            "version 4\nclient 12 {\n    mode Locked\n}\n",
+        ] {
+            assert_eq!(layout_from_kdl(kdl), None, "{:?}", kdl);
+        }
+    }
+
+    #[test]
+    fn unknown_nodes_are_skipped() {
+        let kdl = "version 4\n// a comment\ntheme \"dark\"\nclient 12 {\n    tab 1\n    zoom 2 level=3\n}\n";
+        let (_, clients) = layout_from_kdl(kdl).unwrap();
+        assert_eq!(clients[&12].tab_idx, 1);
+    }
+
+    #[test]
+    fn a_mode_that_cant_be_read_back_is_not_written() {
+        for mode in ["Rename Tab", "Loc\"ked", "", "back\\slash"] {
+            let layout = ClientLayout {
+                mode: Some(String::from(mode)),
+                ..Default::default()
+            };
+            let kdl = layout_to_kdl(4, &BTreeMap::from([(12, layout)]));
+            assert!(!kdl.contains("mode"), "{}", kdl);
+            let (_, clients) = layout_from_kdl(&kdl).unwrap();
+            assert_eq!(clients[&12].mode, None);
+        }
+        let kdl = "version 4\nclient 12 {\n    mode \"Loc\"ked\"\n}\n";
+        assert_eq!(layout_from_kdl(kdl), None);
+    }
+}
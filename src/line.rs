@@ -1,7 +1,12 @@
-use ansi_term::ANSIStrings;
-use unicode_width::UnicodeWidthStr;
+use ansi_term::{ANSIStrings, Style};
+use std::cmp::min;
+use std::collections::BTreeMap;
+use std::iter;
+use unicode_width::UnicodeWidthChar;
 
-use crate::LinePart;
+use crate::config::{Config, ScrollAction, Side};
+use crate::tab::{text_width, truncate_to_width};
+use crate::{LinePart, PartKind};
 use zellij_tile::prelude::*;
 use zellij_tile_utils::style;
 
@@ -11,28 +16,59 @@ fn get_current_title_len(current_title: &[LinePart]) -> usize {
 
 // move elements from before_active and after_active into tabs_to_render while they fit in cols
 // adds collapsed_tabs to the left and right if there's left over tabs that don't fit
-fn populate_tabs_in_tab_line(
+pub(crate) fn populate_tabs_in_tab_line(
     tabs_before_active: &mut Vec<LinePart>,
     tabs_after_active: &mut Vec<LinePart>,
     tabs_to_render: &mut Vec<LinePart>,
     cols: usize,
     palette: Palette,
     _capabilities: PluginCapabilities,
+    config: &Config,
 ) {
     let mut middle_size = get_current_title_len(tabs_to_render);
 
+    // when every tab fits there is nothing to point at, so no width is reserved
+    // for the indicators, which could otherwise push out the last tab that fits
+    let all_tabs_size = middle_size
+        + get_current_title_len(tabs_before_active)
+        + get_current_title_len(tabs_after_active);
+    if all_tabs_size <= cols {
+        tabs_to_render.splice(0..0, tabs_before_active.drain(..));
+        tabs_to_render.append(tabs_after_active);
+        return;
+    }
+
     let mut total_left = 0;
     let mut total_right = 0;
     loop {
         let left_count = tabs_before_active.len();
         let right_count = tabs_after_active.len();
 
-        // left_more_tab_index is the tab to the left of the leftmost visible tab
-        let left_more_tab_index = left_count.saturating_sub(1);
-        let collapsed_left = left_more_message(left_count, palette, "", left_more_tab_index);
+        // left_more_tab_index is the tab to the left of the leftmost visible tab,
+        // taken from the part itself since the tabs might be drawn in reverse
+        let left_more_tab_index = tabs_before_active
+            .last()
+            .and_then(|tab| tab.tab_index)
+            .unwrap_or_default();
+        let collapsed_left = left_more_message(
+            left_count,
+            palette,
+            "",
+            left_more_tab_index,
+            config.left_more_glyph.get(),
+        );
         // right_more_tab_index is the tab to the right of the rightmost visible tab
-        let right_more_tab_index = left_count + tabs_to_render.len();
-        let collapsed_right = right_more_message(right_count, palette, "", right_more_tab_index);
+        let right_more_tab_index = tabs_after_active
+            .first()
+            .and_then(|tab| tab.tab_index)
+            .unwrap_or_default();
+        let collapsed_right = right_more_message(
+            right_count,
+            palette,
+            "",
+            right_more_tab_index,
+            config.right_more_glyph.get(),
+        );
 
         let total_size = collapsed_left.len + middle_size + collapsed_right.len;
 
@@ -95,23 +131,87 @@ fn populate_tabs_in_tab_line(
     }
 }
 
+// end of the visible tabs when the window is kept at start instead of centered
+// around the active tab, None when the active tab would end up out of view
+// unless the window is only being paged through
+pub(crate) fn window_end(
+    tab_lens: &[usize],
+    start: usize,
+    active_tab_index: usize,
+    cols: usize,
+    palette: Palette,
+    config: &Config,
+) -> Option<usize> {
+    let keep_active_visible = config.scroll_action != ScrollAction::PageWindow;
+    if keep_active_visible && start > active_tab_index {
+        return None;
+    }
+    let tab_count = tab_lens.len();
+    let collapsed_left = left_more_message(start, palette, "", 0, config.left_more_glyph.get());
+    let mut end = start;
+    let mut middle_size = 0;
+    while end < tab_count {
+        let collapsed_right = right_more_message(
+            tab_count - end - 1,
+            palette,
+            "",
+            0,
+            config.right_more_glyph.get(),
+        );
+        if collapsed_left.len + middle_size + tab_lens[end] + collapsed_right.len > cols {
+            break;
+        }
+        middle_size += tab_lens[end];
+        end += 1;
+    }
+    (!keep_active_visible || active_tab_index < end).then_some(end)
+}
+
+// the window start that puts the middle of the active tab closest to `middle`,
+// measured from the start of the tabs
+fn centered_window_start(
+    tab_lens: &[usize],
+    active_tab_index: usize,
+    cols: usize,
+    middle: usize,
+    palette: Palette,
+    config: &Config,
+) -> Option<usize> {
+    (0..=active_tab_index)
+        .filter_map(|start| {
+            let end = window_end(tab_lens, start, active_tab_index, cols, palette, config)?;
+            if active_tab_index >= end {
+                return None;
+            }
+            let collapsed_left =
+                left_more_message(start, palette, "", 0, config.left_more_glyph.get());
+            let active_middle = collapsed_left.len
+                + tab_lens[start..active_tab_index].iter().sum::<usize>()
+                + tab_lens[active_tab_index] / 2;
+            Some((active_middle.abs_diff(middle), start))
+        })
+        .min()
+        .map(|(_, start)| start)
+}
+
 fn left_more_message(
     tab_count_to_the_left: usize,
     palette: Palette,
     separator: &str,
     tab_index: usize,
+    glyph: &str,
 ) -> LinePart {
     if tab_count_to_the_left == 0 {
         return LinePart::default();
     }
     let more_text = if tab_count_to_the_left < 10000 {
-        format!(" ← +{} ", tab_count_to_the_left)
+        format!(" {} +{} ", glyph, tab_count_to_the_left)
     } else {
-        " ← +many ".to_string()
+        format!(" {} +many ", glyph)
     };
     // 238
     // chars length plus separator length on both sides
-    let more_text_len = more_text.width() + 2 * separator.width();
+    let more_text_len = text_width(&more_text) + 2 * text_width(separator);
     let (text_color, sep_color) = match palette.theme_hue {
         ThemeHue::Dark => (palette.white, palette.black),
         ThemeHue::Light => (palette.black, palette.white),
@@ -125,6 +225,7 @@ fn left_more_message(
         part: more_styled_text,
         len: more_text_len,
         tab_index: Some(tab_index),
+        kind: PartKind::Overflow,
     }
 }
 
@@ -133,17 +234,18 @@ fn right_more_message(
     palette: Palette,
     separator: &str,
     tab_index: usize,
+    glyph: &str,
 ) -> LinePart {
     if tab_count_to_the_right == 0 {
         return LinePart::default();
     };
     let more_text = if tab_count_to_the_right < 10000 {
-        format!(" +{} → ", tab_count_to_the_right)
+        format!(" +{} {} ", tab_count_to_the_right, glyph)
     } else {
-        " +many → ".to_string()
+        format!(" +many {} ", glyph)
     };
     // chars length plus separator length on both sides
-    let more_text_len = more_text.width() + 2 * separator.width();
+    let more_text_len = text_width(&more_text) + 2 * text_width(separator);
     let (text_color, sep_color) = match palette.theme_hue {
         ThemeHue::Dark => (palette.white, palette.black),
         ThemeHue::Light => (palette.black, palette.white),
@@ -157,10 +259,11 @@ fn right_more_message(
         part: more_styled_text,
         len: more_text_len,
         tab_index: Some(tab_index),
+        kind: PartKind::Overflow,
     }
 }
 
-fn tab_line_prefix(mode: InputMode, palette: Palette, _cols: usize) -> Vec<LinePart> {
+fn tab_line_prefix(mode: InputMode, palette: Palette, config: &Config) -> Vec<LinePart> {
     let bg_color = match palette.theme_hue {
         ThemeHue::Dark => palette.black,
         ThemeHue::Light => palette.white,
@@ -172,8 +275,31 @@ fn tab_line_prefix(mode: InputMode, palette: Palette, _cols: usize) -> Vec<LineP
 
     let mut parts = vec![];
     let mode_part = format!("{:?}", mode).to_uppercase();
+    if config.show_mode_badge {
+        let category = match mode {
+            InputMode::Normal => 0,
+            InputMode::Locked => 1,
+            _ => 2,
+        };
+        let default_bg = [normal_mode_color, locked_mode_color, other_modes_color][category];
+        let (fg, bg) = config.mode_badge_colors[category];
+        let text = format!(" {} ", mode_part);
+        parts.push(LinePart {
+            len: text_width(&text),
+            part: style!(
+                fg.map_or(palette.black, |fg| fg.resolve(palette)),
+                bg.map_or(default_bg, |bg| bg.resolve(palette))
+            )
+            .bold()
+            .paint(text)
+            .to_string(),
+            tab_index: None,
+            kind: PartKind::Static,
+        });
+        return parts;
+    }
     let mode_part_padded = format!(" {} ", mode_part.chars().next().unwrap());
-    let mode_part_len = mode_part_padded.width();
+    let mode_part_len = text_width(&mode_part_padded);
     let mode_part_styled_text = if mode == InputMode::Locked {
         style!(locked_mode_color, bg_color)
             .bold()
@@ -191,20 +317,742 @@ fn tab_line_prefix(mode: InputMode, palette: Palette, _cols: usize) -> Vec<LineP
         part: format!("{}", mode_part_styled_text),
         len: mode_part_len,
         tab_index: None,
+        kind: PartKind::Static,
     });
 
     parts
 }
 
-pub fn tab_line(
+// previously visited tabs, most recent first, each entry focuses its tab when clicked
+fn breadcrumb_parts(tab_history: &[usize], palette: Palette) -> Vec<LinePart> {
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    let mut parts = vec![];
+    for (i, tab_idx) in tab_history.iter().enumerate() {
+        let separator = if i == 0 { " " } else { " ‹ " };
+        let number = tab_idx.to_string();
+        parts.push(LinePart {
+            part: style!(palette.blue, bg_color).paint(separator).to_string(),
+            len: text_width(separator),
+            tab_index: None,
+            kind: PartKind::Static,
+        });
+        parts.push(LinePart {
+            part: style!(palette.blue, bg_color)
+                .bold()
+                .paint(number.as_str())
+                .to_string(),
+            len: text_width(&number),
+            // tab_index is 0 based while the history holds tab positions starting from 1
+            tab_index: Some(tab_idx - 1),
+            kind: PartKind::Breadcrumb,
+        });
+    }
+    if !parts.is_empty() {
+        parts.push(LinePart {
+            part: style!(palette.blue, bg_color).paint(" ").to_string(),
+            len: 1,
+            tab_index: None,
+            kind: PartKind::Static,
+        });
+    }
+    parts
+}
+
+const GROUP_SEPARATOR: &str = "|";
+
+// whether a group of `every` tabs ends between two tabs drawn next to each other,
+// which works out the same whichever way round the tabs are drawn
+fn ends_group(tab: &LinePart, next: Option<&LinePart>, every: usize) -> bool {
+    let Some(next) = next.filter(|next| next.kind == PartKind::Tab) else {
+        return false;
+    };
+    match (tab.tab_index, next.tab_index) {
+        (Some(idx), Some(next_idx)) if tab.kind == PartKind::Tab => {
+            (min(idx, next_idx) + 1) % every == 0
+        }
+        _ => false,
+    }
+}
+
+// separator glyphs drawn after tabs, keyed by tab_index, in card mode only
+// neighbouring inactive tabs are separated so nothing touches the active tab
+fn tab_separators(
+    all_tabs: &[LinePart],
+    active_tab_index: usize,
+    tab_groups: &[String],
+    config: &Config,
+) -> BTreeMap<usize, String> {
+    let mut separators = BTreeMap::new();
+    let tab_count = all_tabs.len();
+    for (i, tab) in all_tabs.iter().enumerate() {
+        let Some(tab_index) = tab.tab_index else {
+            continue;
+        };
+        // the next part in the line, the tabs may be drawn in reverse
+        let next_group = all_tabs
+            .get(i + 1)
+            .and_then(|next| next.tab_index)
+            .and_then(|next| tab_groups.get(next))
+            .filter(|next| tab_groups.get(tab_index) != Some(*next));
+        let separates_inactive_tabs =
+            i != active_tab_index && i + 1 < tab_count && i + 1 != active_tab_index;
+        if config.group_separator_every > 0
+            && ends_group(tab, all_tabs.get(i + 1), config.group_separator_every)
+        {
+            separators.insert(tab_index, GROUP_SEPARATOR.to_string());
+        } else if let Some(next_group) = next_group.filter(|_| config.group_delimiter.is_some()) {
+            let separator = if config.group_labels && !next_group.is_empty() {
+                format!("{} {} ", GROUP_SEPARATOR, next_group)
+            } else {
+                GROUP_SEPARATOR.to_string()
+            };
+            separators.insert(tab_index, separator);
+        } else if config.card_mode && separates_inactive_tabs {
+            separators.insert(tab_index, config.card_separator.get().to_string());
+        } else if let Some(separator) = config
+            .tab_separator
+            .as_ref()
+            .filter(|_| separates_inactive_tabs)
+        {
+            separators.insert(tab_index, separator.get().to_string());
+        }
+    }
+    separators
+}
+
+// splits the width reserved for separators back out of the visible tabs
+// and renders the separators after them
+fn insert_tab_separators(
+    tabs: Vec<LinePart>,
+    separators: &BTreeMap<usize, String>,
+    palette: Palette,
+    config: &Config,
+) -> Vec<LinePart> {
+    let style = match config.tab_separator_color {
+        Some(color) => style!(color.resolve(palette), palette.bg),
+        None => style!(palette.fg, palette.bg).dimmed(),
+    };
+    let mut parts = vec![];
+    for mut tab in tabs {
+        let separator = tab
+            .tab_index
+            .filter(|_| tab.kind == PartKind::Tab)
+            .and_then(|tab_index| separators.get(&tab_index));
+        if let Some(separator) = separator {
+            tab.len -= text_width(separator);
+        }
+        parts.push(tab);
+        if let Some(separator) = separator {
+            parts.push(LinePart {
+                part: style.paint(separator.as_str()).to_string(),
+                len: text_width(separator),
+                tab_index: None,
+                kind: PartKind::Static,
+            });
+        }
+    }
+    parts
+}
+
+// as many of the hints as fit in width columns, in the order given
+fn mode_hints_part(hints: &[String], width: usize, palette: Palette) -> Option<LinePart> {
+    let mut text = String::new();
+    for hint in hints {
+        if text_width(&text) + text_width(hint) + 2 > width {
+            break;
+        }
+        text.push(' ');
+        text.push_str(hint);
+    }
+    if text.is_empty() {
+        return None;
+    }
+    text.push(' ');
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    Some(LinePart {
+        len: text_width(&text),
+        part: style!(palette.fg, bg_color).paint(text).to_string(),
+        tab_index: None,
+        kind: PartKind::Static,
+    })
+}
+
+// follows the last visible tab and opens a new one when clicked
+fn new_tab_part(palette: Palette, config: &Config) -> Option<LinePart> {
+    if !config.new_tab_button {
+        return None;
+    }
+    let text = format!(" {} ", config.new_tab_glyph.get());
+    let color = config
+        .new_tab_color
+        .map_or(palette.green, |color| color.resolve(palette));
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    Some(LinePart {
+        len: text_width(&text),
+        part: style!(color, bg_color).bold().paint(text).to_string(),
+        tab_index: None,
+        kind: PartKind::NewTab,
+    })
+}
+
+// the closest color of the 256 color cube or gray ramp
+fn rgb_to_eight_bit(r: u8, g: u8, b: u8) -> u8 {
+    let cube = |channel: u8| -> u8 {
+        if channel < 48 {
+            0
+        } else if channel < 115 {
+            1
+        } else {
+            (channel - 35) / 40
+        }
+    };
+    if r == g && g == b {
+        return match r {
+            0..=7 => 16,
+            248..=255 => 231,
+            _ => 232 + (r - 8) / 10,
+        };
+    }
+    16 + 36 * cube(r) + 6 * cube(g) + cube(b)
+}
+
+// rewrites every truecolor sgr parameter into its 256 color approximation, zellij
+// doesn't tell plugins whether the terminal has truecolor so true_color decides
+pub(crate) fn eight_bit_colors(text: &str) -> String {
+    let mut converted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("\u{1b}[") {
+        converted.push_str(&rest[..start + 2]);
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find(|c: char| !(c.is_ascii_digit() || c == ';')) else {
+            break;
+        };
+        let params: Vec<&str> = rest[..end].split(';').collect();
+        let mut i = 0;
+        let mut rewritten = vec![];
+        while i < params.len() {
+            let channels = params.get(i + 2..i + 5).map(|channels| {
+                channels
+                    .iter()
+                    .filter_map(|channel| channel.parse::<u8>().ok())
+                    .collect::<Vec<u8>>()
+            });
+            match (params[i], params.get(i + 1), channels) {
+                ("38" | "48", Some(&"2"), Some(rgb)) if rgb.len() == 3 => {
+                    let color = rgb_to_eight_bit(rgb[0], rgb[1], rgb[2]);
+                    rewritten.push(format!("{};5;{}", params[i], color));
+                    i += 5;
+                }
+                (param, _, _) => {
+                    rewritten.push(param.to_string());
+                    i += 1;
+                }
+            }
+        }
+        converted.push_str(&rewritten.join(";"));
+        rest = &rest[end..];
+    }
+    converted.push_str(rest);
+    converted
+}
+
+// strftime-like with %Y %m %d %H %M %S, seconds are counted from the unix epoch
+// in the wanted time zone since a wasm plugin can't read the local one
+pub(crate) fn format_clock(format: &str, seconds: i64) -> String {
+    let days = seconds.div_euclid(86400);
+    let time = seconds.rem_euclid(86400);
+    // days to a civil date, from Howard Hinnant's date algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let mut text = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('Y') => text.push_str(&year.to_string()),
+            Some('m') => text.push_str(&format!("{:02}", month)),
+            Some('d') => text.push_str(&format!("{:02}", day)),
+            Some('H') => text.push_str(&format!("{:02}", time / 3600)),
+            Some('M') => text.push_str(&format!("{:02}", time % 3600 / 60)),
+            Some('S') => text.push_str(&format!("{:02}", time % 60)),
+            Some(other) => {
+                text.push('%');
+                text.push(other);
+            }
+            None => text.push('%'),
+        }
+    }
+    text
+}
+
+fn clock_part(clock: &str, palette: Palette) -> LinePart {
+    let text = format!(" {} ", clock);
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    LinePart {
+        len: text_width(&text),
+        part: style!(palette.fg, bg_color).paint(text).to_string(),
+        tab_index: None,
+        kind: PartKind::Static,
+    }
+}
+
+// static segment with the plugin version or the configured brand, never clickable
+fn brand_part(config: &Config, palette: Palette) -> Option<LinePart> {
+    let text = match &config.brand {
+        Some(brand) => format!(" {} ", brand),
+        None if config.show_version => format!(" v{} ", env!("CARGO_PKG_VERSION")),
+        None => return None,
+    };
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    Some(LinePart {
+        len: text_width(&text),
+        part: style!(palette.fg, bg_color)
+            .dimmed()
+            .paint(text)
+            .to_string(),
+        tab_index: None,
+        kind: PartKind::Static,
+    })
+}
+
+// title of the focused pane in the active tab, cut to the configured width
+fn pane_title_part(title: &str, max_width: usize, palette: Palette) -> Option<LinePart> {
+    let title = title.trim();
+    if title.is_empty() {
+        return None;
+    }
+    let text = format!(" {} ", truncate_to_width(title, max_width));
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    Some(LinePart {
+        len: text_width(&text),
+        part: style!(palette.green, bg_color)
+            .italic()
+            .paint(text)
+            .to_string(),
+        tab_index: None,
+        kind: PartKind::Static,
+    })
+}
+
+// clicking it switches to the next session like the switch_session pipe does
+fn more_sessions_part(
+    other_sessions: usize,
+    palette: Palette,
+    config: &Config,
+) -> Option<LinePart> {
+    if !config.more_sessions || other_sessions == 0 {
+        return None;
+    }
+    let text = format!(" {} {} ", config.more_sessions_glyph.get(), other_sessions);
+    Some(LinePart {
+        len: text_width(&text),
+        part: style!(palette.black, palette.blue).paint(text).to_string(),
+        tab_index: None,
+        kind: PartKind::Sessions,
+    })
+}
+
+// a solo session only gets the dimmed glyph, a shared one also the client count
+fn shared_part(clients: usize, palette: Palette, config: &Config) -> LinePart {
+    let glyph = config.shared_glyph.get();
+    let (text, style) = if clients > 1 {
+        (
+            format!(" {} {} ", glyph, clients),
+            style!(palette.black, palette.magenta).bold(),
+        )
+    } else {
+        let bg_color = match palette.theme_hue {
+            ThemeHue::Dark => palette.black,
+            ThemeHue::Light => palette.white,
+        };
+        (
+            format!(" {} ", glyph),
+            style!(palette.fg, bg_color).dimmed(),
+        )
+    };
+    LinePart {
+        len: text_width(&text),
+        part: style.paint(text).to_string(),
+        tab_index: None,
+        kind: PartKind::Static,
+    }
+}
+
+fn dirty_part(palette: Palette, config: &Config) -> LinePart {
+    let text = format!(" {} ", config.dirty_glyph.get());
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    LinePart {
+        len: text_width(&text),
+        part: style!(palette.orange, bg_color)
+            .bold()
+            .paint(text)
+            .to_string(),
+        tab_index: None,
+        kind: PartKind::Static,
+    }
+}
+
+fn pane_count_part(pane_count: usize, palette: Palette, config: &Config) -> LinePart {
+    let text = format!(" {} {} ", config.pane_count_glyph.get(), pane_count);
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    LinePart {
+        len: text_width(&text),
+        part: style!(palette.fg, bg_color).paint(text).to_string(),
+        tab_index: None,
+        kind: PartKind::Static,
+    }
+}
+
+fn tab_counter_part(active: usize, total: usize, palette: Palette) -> LinePart {
+    let text = format!(" {}/{} ", active, total);
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    LinePart {
+        len: text_width(&text),
+        part: style!(palette.fg, bg_color).bold().paint(text).to_string(),
+        tab_index: None,
+        kind: PartKind::Static,
+    }
+}
+
+fn session_name_style(palette: Palette, config: &Config) -> Style {
+    style!(
+        config
+            .session_name_fg
+            .map_or(palette.black, |fg| fg.resolve(palette)),
+        config
+            .session_name_bg
+            .map_or(palette.cyan, |bg| bg.resolve(palette))
+    )
+}
+
+// session segment that always takes exactly `width` columns, the name is cut if it doesn't fit
+fn pinned_session_part(name: &str, width: usize, palette: Palette, config: &Config) -> LinePart {
+    let width = if width == 0 {
+        text_width(name) + 2
+    } else {
+        width
+    };
+    let mut name_part = String::from(" ");
+    for c in name.chars() {
+        if text_width(&name_part) + c.width().unwrap_or(0) + 1 > width {
+            break;
+        }
+        name_part.push(c);
+    }
+    while text_width(&name_part) < width {
+        name_part.push(' ');
+    }
+    let name_part_len = text_width(&name_part);
+    LinePart {
+        part: session_name_style(palette, config)
+            .paint(name_part)
+            .to_string(),
+        len: name_part_len,
+        tab_index: None,
+        kind: PartKind::SessionName,
+    }
+}
+
+// the columns a row is laid out in between its paddings
+pub(crate) fn padded_cols(cols: usize, config: &Config) -> usize {
+    cols.saturating_sub(config.left_padding + config.right_padding)
+}
+
+// blank cells in the bar background at both ends of a row laid out in
+// padded_cols, on the left they go after a pinned session so it keeps the edge
+pub(crate) fn pad_line(
+    mut parts: Vec<LinePart>,
+    pinned: bool,
+    cols: usize,
+    palette: Palette,
+    config: &Config,
+) -> Vec<LinePart> {
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    let padding = |len: usize| LinePart {
+        part: style!(palette.fg, bg_color)
+            .paint(" ".repeat(len))
+            .to_string(),
+        len,
+        tab_index: None,
+        kind: PartKind::Static,
+    };
+    // a row narrower than its paddings is all padding
+    let left = min(config.left_padding, cols);
+    let right = min(config.right_padding, cols - left);
+    if left > 0 {
+        parts.insert(usize::from(pinned).min(parts.len()), padding(left));
+    }
+    if right > 0 {
+        parts.push(padding(right));
+    }
+    parts
+}
+
+fn filler_part(len: usize, palette: Palette) -> LinePart {
+    let mut buffer = String::new();
+    for _ in 0..len {
+        buffer.push_str(&style!(palette.black, palette.black).paint(" ").to_string());
+    }
+    LinePart {
+        part: buffer,
+        len,
+        tab_index: None,
+        kind: PartKind::Filler,
+    }
+}
+
+// shows the page of `slots` tabs holding the active tab, every tab was already
+// rendered to exactly slot_width so a tab always covers the same columns
+pub fn grid_line(
+    all_tabs: Vec<LinePart>,
+    active_tab_index: usize,
+    slots: usize,
+    slot_width: usize,
+    cols: usize,
+    palette: Palette,
+) -> Vec<LinePart> {
+    let page_start = active_tab_index / slots * slots;
+    let mut parts: Vec<LinePart> = all_tabs.into_iter().skip(page_start).take(slots).collect();
+    while parts.len() < slots {
+        parts.push(filler_part(slot_width, palette));
+    }
+    let used = slots * slot_width;
+    if used < cols {
+        parts.push(filler_part(cols - used, palette));
+    }
+    parts
+}
+
+// room for " used/available " when both numbers are as wide as cols
+pub fn debug_overlay_width(cols: usize) -> usize {
+    2 * cols.to_string().len() + 3
+}
+
+// reports how many of the available columns the tab line draws, not counting the filler
+pub fn debug_overlay(tab_line: &[LinePart], cols: usize, palette: Palette) -> LinePart {
+    let width = debug_overlay_width(cols);
+    let used: usize = tab_line
+        .iter()
+        .filter(|part| part.kind != PartKind::Filler)
+        .map(|part| part.len)
+        .sum();
+    let text = format!("{:>width$}", format!("{}/{} ", used, cols), width = width);
+    LinePart {
+        part: style!(palette.black, palette.red).paint(text).to_string(),
+        len: width,
+        tab_index: None,
+        kind: PartKind::Static,
+    }
+}
+
+// the narrowest bar tab_line can lay out, a one digit active tab with a three
+// cell overflow marker on either side of it
+pub(crate) const MIN_BAR_COLS: usize = 9;
+
+// what a pane narrower than MIN_BAR_COLS gets instead, the active tab's number
+// cut at cols and the background filled behind it
+pub(crate) fn minimal_line(
+    number: usize,
+    position: usize,
+    cols: usize,
+    palette: Palette,
+) -> Vec<LinePart> {
+    let text = truncate_to_width(&format!(" {} ", number), cols);
+    let len = text_width(&text);
+    vec![
+        LinePart {
+            part: style!(palette.black, palette.green)
+                .bold()
+                .paint(text)
+                .to_string(),
+            len,
+            tab_index: Some(position),
+            kind: PartKind::Tab,
+        },
+        filler_part(cols - len, palette),
+    ]
+}
+
+// the placeholder is cut at cols so it never wraps onto a second line
+pub fn placeholder_line(
+    placeholder: &str,
+    session_name: Option<&str>,
+    cols: usize,
+    palette: Palette,
+) -> Vec<LinePart> {
+    let text = format!(
+        " {} ",
+        placeholder.replace("{session}", session_name.unwrap_or_default())
+    );
+    let visible_text = truncate_to_width(&text, cols);
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    vec![LinePart {
+        len: text_width(&visible_text),
+        part: style!(palette.fg, bg_color)
+            .dimmed()
+            .paint(visible_text)
+            .to_string(),
+        tab_index: None,
+        kind: PartKind::Static,
+    }]
+}
+
+// everything drawn on the bar besides the tabs themselves
+pub struct BarInfo<'a> {
+    pub session_name: Option<&'a str>,
+    pub mode: InputMode,
+    pub tab_history: &'a [usize],
+    pub pane_title: Option<&'a str>,
+    pub other_sessions: usize,
+    // first visible tab of a window the user moved by hand
+    pub window_start: Option<usize>,
+    // the mode moves to the header row of a multi-row bar
+    pub show_mode: bool,
+    pub pane_count: Option<usize>,
+    // the active tab's number and how many tabs there are
+    pub tab_counter: Option<(usize, usize)>,
+    pub layout_dirty: bool,
+    // clients attached to the session, None until zellij listed them
+    pub clients: Option<usize>,
+    // `key action` pairs bound in the current mode
+    pub mode_hints: &'a [String],
+    pub clock: Option<&'a str>,
+    // the group of each tab by position, what its name has before group_delimiter
+    pub tab_groups: &'a [String],
+}
+
+fn session_name_part(name: &str, palette: Palette, config: &Config) -> LinePart {
+    let name_part = format!(" {} ", name);
+    let name_part_len = text_width(&name_part) - 1;
+    let name_part_styled_text = session_name_style(palette, config).paint(name_part);
+    LinePart {
+        part: name_part_styled_text.to_string(),
+        len: name_part_len,
+        tab_index: None,
+        kind: PartKind::SessionName,
+    }
+}
+
+// first row of a multi-row bar, the mode and the session the tabs belong to
+pub fn header_line(
     session_name: Option<&str>,
+    mode: InputMode,
+    cols: usize,
+    palette: Palette,
+    config: &Config,
+) -> Vec<LinePart> {
+    let mut parts = tab_line_prefix(mode, palette, config);
+    if let Some(name) = session_name {
+        parts.push(session_name_part(name, palette, config));
+    }
+    let used = get_current_title_len(&parts);
+    if used <= cols {
+        parts.push(filler_part(cols - used, palette));
+    }
+    parts
+}
+
+// last row of a multi-row bar, where the active tab is and what its focused pane runs
+pub fn status_line(
+    active_tab_idx: usize,
+    tab_count: usize,
+    pane_title: Option<&str>,
+    cols: usize,
+    palette: Palette,
+) -> Vec<LinePart> {
+    let bg_color = match palette.theme_hue {
+        ThemeHue::Dark => palette.black,
+        ThemeHue::Light => palette.white,
+    };
+    let position = format!(" {}/{} ", active_tab_idx, tab_count);
+    let mut parts = vec![LinePart {
+        len: text_width(&position),
+        part: style!(palette.fg, bg_color)
+            .bold()
+            .paint(position)
+            .to_string(),
+        tab_index: None,
+        kind: PartKind::Static,
+    }];
+    let used = get_current_title_len(&parts);
+    let title =
+        pane_title.and_then(|title| pane_title_part(title, cols.saturating_sub(used + 2), palette));
+    if let Some(title) = title {
+        parts.push(title);
+    }
+    let used = get_current_title_len(&parts);
+    if used <= cols {
+        parts.push(filler_part(cols - used, palette));
+    }
+    parts
+}
+
+// lays the tabs out in cols columns around the segments described by bar, needs
+// nothing from the plugin state: all_tabs are the parts built by tab_style in tab
+// order and active_tab_index is the index of the active one among them
+pub(crate) fn tab_line(
+    bar: &BarInfo,
     mut all_tabs: Vec<LinePart>,
     active_tab_index: usize,
     cols: usize,
     palette: Palette,
     capabilities: PluginCapabilities,
-    mode: InputMode,
+    config: &Config,
 ) -> Vec<LinePart> {
+    // the padding is taken off the columns everything else is laid out in
+    let bar_cols = cols;
+    let cols = padded_cols(cols, config);
+    let session_name = bar.session_name;
+    let separators = tab_separators(&all_tabs, active_tab_index, bar.tab_groups, config);
+    // the separator width is counted as part of the tab while deciding which tabs fit
+    for tab in all_tabs.iter_mut() {
+        if let Some(separator) = tab.tab_index.and_then(|idx| separators.get(&idx)) {
+            tab.len += text_width(separator);
+        }
+    }
     let mut tabs_after_active = all_tabs.split_off(active_tab_index);
     let mut tabs_before_active = all_tabs;
     let active_tab = if !tabs_after_active.is_empty() {
@@ -212,60 +1060,822 @@ pub fn tab_line(
     } else {
         tabs_before_active.pop().unwrap()
     };
-    let mut prefix = tab_line_prefix(mode, palette, cols);
+    let mut prefix = if bar.show_mode {
+        tab_line_prefix(bar.mode, palette, config)
+    } else {
+        vec![]
+    };
+    let mut suffix = vec![];
 
-    if let Some(name) = session_name {
-        let name_part = format!(" {} ", name);
-        let name_part_len = name_part.width() - 1;
-        let name_part_styled_text = style!(palette.black, palette.cyan).paint(name_part);
-        prefix.push(LinePart {
-            part: name_part_styled_text.to_string(),
-            len: name_part_len,
-            tab_index: None,
-        })
+    let pin_session = config.pin_session_left && session_name.is_some();
+    if let Some(name) = session_name.filter(|_| pin_session) {
+        prefix.insert(
+            0,
+            pinned_session_part(name, config.session_width, palette, config),
+        );
+    } else if let Some(name) = session_name {
+        // on the right it goes after the filler, the click offsets add up the same either way
+        match config.session_name_position {
+            Side::Left => prefix.insert(0, session_name_part(name, palette, config)),
+            Side::Right => suffix.push(session_name_part(name, palette, config)),
+        }
+    }
+    prefix.append(&mut breadcrumb_parts(bar.tab_history, palette));
+    if let Some(pane_count) = bar.pane_count {
+        suffix.insert(0, pane_count_part(pane_count, palette, config));
+    }
+    if let Some((active, total)) = bar.tab_counter {
+        suffix.insert(0, tab_counter_part(active, total, palette));
     }
+    if let Some(sessions) = more_sessions_part(bar.other_sessions, palette, config) {
+        match config.more_sessions_position {
+            Side::Left => prefix.push(sessions),
+            Side::Right => suffix.push(sessions),
+        }
+    }
+    if let Some(title) = bar.pane_title {
+        if let Some(title_part) = pane_title_part(title, config.pane_title_max_width, palette) {
+            match config.pane_title_position {
+                Side::Left => prefix.push(title_part),
+                Side::Right => suffix.insert(0, title_part),
+            }
+        }
+    }
+    if let Some(clients) = bar.clients.filter(|_| config.shared_indicator) {
+        match config.shared_position {
+            Side::Left => prefix.push(shared_part(clients, palette, config)),
+            Side::Right => suffix.insert(0, shared_part(clients, palette, config)),
+        }
+    }
+    if bar.layout_dirty {
+        match config.dirty_position {
+            Side::Left => prefix.push(dirty_part(palette, config)),
+            Side::Right => suffix.insert(0, dirty_part(palette, config)),
+        }
+    }
+    if let Some(brand) = brand_part(config, palette) {
+        match config.brand_position {
+            Side::Left => prefix.push(brand),
+            Side::Right => suffix.push(brand),
+        }
+    }
+
+    // the clock stays at the right edge, the hints go before it once the tabs are laid out
+    let clock = bar.clock.map(|clock| clock_part(clock, palette));
+    let hints_at = suffix.len();
+    suffix.extend(clock);
 
-    let prefix_len = get_current_title_len(&prefix);
+    let new_tab_button = new_tab_part(palette, config);
+    let prefix_len = get_current_title_len(&prefix)
+        + get_current_title_len(&suffix)
+        + new_tab_button.as_ref().map_or(0, |button| button.len);
 
     // if active tab alone won't fit in cols, don't draw any tabs
     if prefix_len + active_tab.len > cols {
-        return prefix;
+        prefix.append(&mut suffix);
+        return pad_line(prefix, pin_session, bar_cols, palette, config);
     }
 
-    let mut tabs_to_render = vec![active_tab];
+    let tab_lens: Vec<usize> = tabs_before_active
+        .iter()
+        .chain(iter::once(&active_tab))
+        .chain(tabs_after_active.iter())
+        .map(|tab| tab.len)
+        .collect();
+    let tabs_width = cols.saturating_sub(prefix_len);
+    let centered_start = if config.center_active_tab && tab_lens.iter().sum::<usize>() > tabs_width
+    {
+        centered_window_start(
+            &tab_lens,
+            tabs_before_active.len(),
+            tabs_width,
+            (cols / 2).saturating_sub(get_current_title_len(&prefix)),
+            palette,
+            config,
+        )
+    } else {
+        None
+    };
+    let active = tabs_before_active.len();
+    let window = if config.minimal_tabs {
+        Some((active.saturating_sub(1), min(active + 2, tab_lens.len())))
+    } else {
+        bar.window_start.or(centered_start).and_then(|start| {
+            let end = window_end(
+                &tab_lens,
+                start,
+                tabs_before_active.len(),
+                tabs_width,
+                palette,
+                config,
+            )?;
+            Some((start, end))
+        })
+    };
 
-    populate_tabs_in_tab_line(
-        &mut tabs_before_active,
-        &mut tabs_after_active,
-        &mut tabs_to_render,
-        cols.saturating_sub(prefix_len),
-        palette,
-        capabilities,
-    );
+    let mut tabs_to_render = if let Some((start, end)) = window {
+        let mut all_tabs = tabs_before_active;
+        all_tabs.push(active_tab);
+        all_tabs.append(&mut tabs_after_active);
+        let tabs_right = all_tabs.split_off(end);
+        let mut tabs_to_render = all_tabs.split_off(start);
+        let tabs_left = all_tabs;
+        // the fixed window of minimal_tabs marks what it hides more quietly
+        let (left_glyph, right_glyph) = if config.minimal_tabs {
+            ("…", "…")
+        } else {
+            (config.left_more_glyph.get(), config.right_more_glyph.get())
+        };
+        tabs_to_render.insert(
+            0,
+            left_more_message(
+                tabs_left.len(),
+                palette,
+                "",
+                tabs_left
+                    .last()
+                    .and_then(|tab| tab.tab_index)
+                    .unwrap_or_default(),
+                left_glyph,
+            ),
+        );
+        tabs_to_render.push(right_more_message(
+            tabs_right.len(),
+            palette,
+            "",
+            tabs_right
+                .first()
+                .and_then(|tab| tab.tab_index)
+                .unwrap_or_default(),
+            right_glyph,
+        ));
+        tabs_to_render
+    } else {
+        let mut tabs_to_render = vec![active_tab];
+        populate_tabs_in_tab_line(
+            &mut tabs_before_active,
+            &mut tabs_after_active,
+            &mut tabs_to_render,
+            cols.saturating_sub(prefix_len),
+            palette,
+            capabilities,
+            config,
+        );
+        tabs_to_render
+    };
+    if !separators.is_empty() {
+        tabs_to_render = insert_tab_separators(tabs_to_render, &separators, palette, config);
+    }
     prefix.append(&mut tabs_to_render);
+    prefix.extend(new_tab_button);
 
-    if session_name.is_some() {
-        let session_part = prefix.remove(1);
-        prefix.push(session_part);
+    // the hints only get the columns no tab wanted
+    let current_title_len = get_current_title_len(&prefix) + get_current_title_len(&suffix);
+    let leftover = cols.saturating_sub(current_title_len);
+    if let Some(hints) = mode_hints_part(bar.mode_hints, leftover, palette) {
+        suffix.insert(hints_at, hints);
     }
 
-    let current_title_len = get_current_title_len(&prefix);
+    let current_title_len = get_current_title_len(&prefix) + get_current_title_len(&suffix);
     if current_title_len < cols {
         let remaining_space = cols - current_title_len;
+        // the filler keeps its width either way, so clicks still add up to the same
+        // tabs, on the right it goes after a pinned session
+        match config.align {
+            Side::Left => prefix.push(filler_part(remaining_space, palette)),
+            Side::Right => prefix.insert(
+                usize::from(pin_session),
+                filler_part(remaining_space, palette),
+            ),
+        }
+    }
+    prefix.append(&mut suffix);
+
+    pad_line(prefix, pin_session, bar_cols, palette, config)
+}
 
-        let mut buffer = String::new();
-        for _ in 0..remaining_space {
-            buffer.push_str(&style!(palette.black, palette.black).paint(" ").to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tab::{get_clicked_line_part, get_tab_to_focus, tab_style, TabMarks};
+
+    fn bar<'a>() -> BarInfo<'a> {
+        BarInfo {
+            session_name: None,
+            mode: InputMode::Normal,
+            tab_history: &[],
+            pane_title: None,
+            other_sessions: 0,
+            window_start: None,
+            show_mode: false,
+            pane_count: None,
+            tab_counter: None,
+            layout_dirty: false,
+            clients: None,
+            mode_hints: &[],
+            clock: None,
+            tab_groups: &[],
         }
-        prefix.insert(
-            prefix.len() - 1,
-            LinePart {
-                part: buffer,
-                len: remaining_space,
-                tab_index: None,
-            },
+    }
+
+    fn tab_info(position: usize, active: usize) -> TabInfo {
+        TabInfo {
+            position,
+            name: format!("tab {}", position + 1),
+            active: position == active,
+            ..Default::default()
+        }
+    }
+
+    fn tabs(count: usize, active: usize, config: &Config) -> Vec<LinePart> {
+        (0..count)
+            .map(|position| {
+                let tab = tab_info(position, active);
+                tab_style(
+                    tab.name.clone(),
+                    &tab,
+                    Palette::default(),
+                    config,
+                    None,
+                    InputMode::Normal,
+                    TabMarks::default(),
+                )
+            })
+            .collect()
+    }
+
+    fn tab_indices(line: &[LinePart]) -> Vec<usize> {
+        line.iter()
+            .filter(|part| part.kind == PartKind::Tab)
+            .filter_map(|part| part.tab_index)
+            .collect()
+    }
+
+    fn line(bar: &BarInfo, tabs: Vec<LinePart>, active: usize, cols: usize) -> Vec<LinePart> {
+        line_with(bar, tabs, active, cols, &Config::default())
+    }
+
+    fn line_with(
+        bar: &BarInfo,
+        tabs: Vec<LinePart>,
+        active: usize,
+        cols: usize,
+        config: &Config,
+    ) -> Vec<LinePart> {
+        tab_line(
+            bar,
+            tabs,
+            active,
+            cols,
+            Palette::default(),
+            PluginCapabilities::default(),
+            config,
+        )
+    }
+
+    #[test]
+    fn clicks_map_past_the_reserved_session_width() {
+        let config = Config {
+            pin_session_left: true,
+            session_width: 12,
+            ..Default::default()
+        };
+        let bar = BarInfo {
+            session_name: Some("work"),
+            ..bar()
+        };
+        let all_tabs = tabs(3, 0, &config);
+        let first_width = all_tabs[0].len;
+        let parts = line_with(&bar, all_tabs, 0, 80, &config);
+        assert_eq!(parts[0].kind, PartKind::SessionName);
+        assert_eq!(parts[0].len, 12);
+        // the session segment itself focuses nothing
+        assert_eq!(get_tab_to_focus(&parts, 1, 11), None);
+        assert_eq!(parts[1].tab_index, Some(0));
+        assert_eq!(get_tab_to_focus(&parts, 1, 12 + first_width), Some(2));
+        assert_eq!(get_tab_to_focus(&parts, 1, 12 + first_width - 1), None);
+    }
+
+    #[test]
+    fn a_long_session_name_is_cut_to_the_reserved_width() {
+        let config = Config {
+            pin_session_left: true,
+            session_width: 6,
+            ..Default::default()
+        };
+        let bar = BarInfo {
+            session_name: Some("a-very-long-session"),
+            ..bar()
+        };
+        let parts = line_with(&bar, tabs(2, 0, &config), 0, 80, &config);
+        assert_eq!(parts[0].len, 6);
+        assert_eq!(get_current_title_len(&parts), 80);
+    }
+
+    #[test]
+    fn the_version_segment_is_counted_in_the_width() {
+        let config = Config {
+            show_version: true,
+            ..Default::default()
+        };
+        let parts = line_with(&bar(), tabs(2, 0, &config), 0, 80, &config);
+        let version = format!(" v{} ", env!("CARGO_PKG_VERSION"));
+        let brand = parts
+            .iter()
+            .find(|part| part.part.contains(&version))
+            .unwrap();
+        assert_eq!(brand.len, version.len());
+        assert_eq!(brand.tab_index, None);
+        assert_eq!(get_current_title_len(&parts), 80);
+    }
+
+    #[test]
+    fn a_brand_replaces_the_version() {
+        let config = Config {
+            show_version: true,
+            brand: Some("acme".to_string()),
+            brand_position: Side::Left,
+            ..Default::default()
+        };
+        let parts = line_with(&bar(), tabs(2, 0, &config), 0, 80, &config);
+        assert!(parts[0].part.contains(" acme "));
+        assert_eq!(parts[0].len, 6);
+        assert!(!parts.iter().any(|part| part.part.contains(" v0")));
+        assert_eq!(get_current_title_len(&parts), 80);
+    }
+
+    #[test]
+    fn group_separators_follow_every_fifth_tab() {
+        let config = Config {
+            group_separator_every: 5,
+            ..Default::default()
+        };
+        let all_tabs = tabs(12, 0, &config);
+        let widths: Vec<usize> = all_tabs.iter().map(|tab| tab.len).collect();
+        let parts = line_with(&bar(), all_tabs, 0, 200, &config);
+        let separators: Vec<usize> = parts
+            .iter()
+            .enumerate()
+            .filter(|(_, part)| part.part.contains(GROUP_SEPARATOR))
+            .map(|(i, _)| i)
+            .collect();
+        let tab_at = |idx: usize| {
+            parts
+                .iter()
+                .position(|part| part.kind == PartKind::Tab && part.tab_index == Some(idx))
+                .unwrap()
+        };
+        // after the 5th and the 10th tab, nothing after the last one
+        assert_eq!(separators, vec![tab_at(4) + 1, tab_at(9) + 1]);
+        // the width reserved for a separator goes back to it
+        assert_eq!(parts[tab_at(4)].len, widths[4]);
+        assert_eq!(parts[tab_at(4) + 1].len, GROUP_SEPARATOR.len());
+        assert_eq!(get_current_title_len(&parts), 200);
+    }
+
+    #[test]
+    fn an_empty_session_renders_the_placeholder() {
+        let parts = placeholder_line("no tabs in {session}", Some("work"), 40, Palette::default());
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].part.contains(" no tabs in work "));
+        assert_eq!(parts[0].len, 17);
+        // a narrow bar cuts the placeholder instead of dropping it
+        let parts = placeholder_line("no tabs in {session}", Some("work"), 6, Palette::default());
+        assert_eq!(parts[0].len, 6);
+    }
+
+    #[test]
+    fn debug_overlay_reports_the_width_the_parts_use() {
+        let mut parts = tabs(3, 0, &Config::default());
+        let used: usize = parts.iter().map(|part| part.len).sum();
+        parts.push(LinePart {
+            part: " ".repeat(20),
+            len: 20,
+            tab_index: None,
+            kind: PartKind::Filler,
+        });
+        let overlay = debug_overlay(&parts, 120, Palette::default());
+        assert!(overlay.part.contains(&format!("{}/120 ", used)));
+        assert_eq!(overlay.len, debug_overlay_width(120));
+    }
+
+    #[test]
+    fn reversed_tabs_keep_group_separators_on_the_same_boundaries() {
+        let config = Config {
+            group_separator_every: 5,
+            ..Default::default()
+        };
+        let mut all_tabs = tabs(12, 0, &config);
+        all_tabs.reverse();
+        let parts = line_with(&bar(), all_tabs, 11, 200, &config);
+        let before_separators: Vec<Option<usize>> = parts
+            .windows(2)
+            .filter(|pair| pair[1].part.contains(GROUP_SEPARATOR))
+            .map(|pair| pair[0].tab_index)
+            .collect();
+        // drawn 11 to 0, the boundary between the 5th and 6th tab now follows idx 5
+        assert_eq!(before_separators, vec![Some(10), Some(5)]);
+        assert_eq!(get_current_title_len(&parts), 200);
+    }
+
+    #[test]
+    fn clicking_the_leftmost_reversed_tab_focuses_the_last_tab() {
+        let config = Config::default();
+        let mut all_tabs = tabs(4, 0, &config);
+        all_tabs.reverse();
+        let parts = line(&bar(), all_tabs, 3, 80);
+        assert_eq!(tab_indices(&parts), vec![3, 2, 1, 0]);
+        // tabs are indexed starting from 1
+        assert_eq!(get_tab_to_focus(&parts, 1, 0), Some(4));
+        let last = get_current_title_len(&parts[..3]);
+        assert_eq!(get_tab_to_focus(&parts, 1, last), None);
+    }
+
+    fn slotted_tabs(count: usize, active: usize, slot_width: usize) -> Vec<LinePart> {
+        (0..count)
+            .map(|position| {
+                let tab = tab_info(position, active);
+                tab_style(
+                    tab.name.clone(),
+                    &tab,
+                    Palette::default(),
+                    &Config::default(),
+                    Some(slot_width),
+                    InputMode::Normal,
+                    TabMarks::default(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn grid_slots_hold_the_page_of_the_active_tab() {
+        let parts = grid_line(slotted_tabs(7, 5, 10), 5, 4, 10, 43, Palette::default());
+        // the second page holds tabs 5 to 7 and an empty slot, then the remainder
+        assert_eq!(tab_indices(&parts), vec![4, 5, 6]);
+        let lens: Vec<usize> = parts.iter().map(|part| part.len).collect();
+        assert_eq!(lens, vec![10, 10, 10, 10, 3]);
+        assert_eq!(parts[3].kind, PartKind::Filler);
+    }
+
+    #[test]
+    fn clicks_map_to_the_slot_under_them() {
+        let parts = grid_line(slotted_tabs(7, 5, 10), 5, 4, 10, 43, Palette::default());
+        assert_eq!(get_tab_to_focus(&parts, 6, 0), Some(5));
+        assert_eq!(get_tab_to_focus(&parts, 6, 29), Some(7));
+        // the active slot, the empty slot and the remainder focus nothing
+        assert_eq!(get_tab_to_focus(&parts, 6, 15), None);
+        assert_eq!(get_tab_to_focus(&parts, 6, 35), None);
+        assert_eq!(get_tab_to_focus(&parts, 6, 41), None);
+    }
+
+    #[test]
+    fn card_mode_separates_only_inactive_neighbours() {
+        let config = Config {
+            card_mode: true,
+            ..Default::default()
+        };
+        let all_tabs = tabs(5, 2, &config);
+        assert_eq!(
+            tab_separators(&all_tabs, 2, &[], &config)
+                .into_keys()
+                .collect::<Vec<_>>(),
+            vec![0, 3]
         );
     }
 
-    prefix
+    #[test]
+    fn card_mode_widths_around_the_active_tab() {
+        let config = Config {
+            card_mode: true,
+            ..Default::default()
+        };
+        let all_tabs = tabs(5, 2, &config);
+        let inactive = all_tabs[0].len;
+        // the lifted tab has a column of extra padding on either side
+        assert_eq!(all_tabs[2].len, inactive + 2);
+        let tabs_width = get_current_title_len(&all_tabs);
+        let parts = line_with(&bar(), all_tabs, 2, 200, &config);
+        let drawn: Vec<(Option<usize>, usize)> = parts
+            .iter()
+            .filter(|part| part.kind != PartKind::Filler)
+            .map(|part| (part.tab_index, part.len))
+            .collect();
+        assert_eq!(
+            drawn,
+            vec![
+                (Some(0), inactive),
+                (None, 1),
+                (Some(1), inactive),
+                (Some(2), inactive + 2),
+                (Some(3), inactive),
+                (None, 1),
+                (Some(4), inactive),
+            ]
+        );
+        assert_eq!(
+            drawn.iter().map(|(_, len)| len).sum::<usize>(),
+            tabs_width + 2
+        );
+    }
+
+    #[test]
+    fn the_more_sessions_segment_shows_the_count_and_takes_the_click() {
+        let config = Config {
+            more_sessions: true,
+            more_sessions_position: Side::Left,
+            ..Default::default()
+        };
+        let with_sessions = BarInfo {
+            other_sessions: 2,
+            ..bar()
+        };
+        let parts = line_with(&with_sessions, tabs(2, 0, &config), 0, 80, &config);
+        let sessions = parts
+            .iter()
+            .position(|part| part.kind == PartKind::Sessions)
+            .unwrap();
+        assert!(parts[sessions].part.contains(" 2 "));
+        let col: usize = parts[..sessions].iter().map(|part| part.len).sum();
+        let clicked = get_clicked_line_part(&parts, col).map(|part| part.kind);
+        assert_eq!(clicked, Some(PartKind::Sessions));
+        // nothing is drawn without other sessions
+        let parts = line_with(&bar(), tabs(2, 0, &config), 0, 80, &config);
+        assert!(!parts.iter().any(|part| part.kind == PartKind::Sessions));
+    }
+
+    #[test]
+    fn a_shared_session_shows_its_client_count() {
+        let config = Config::default();
+        let shared = shared_part(3, Palette::default(), &config);
+        assert!(shared.part.contains(" ⇄ 3 "));
+        assert_eq!(shared.len, 5);
+        // a solo session only gets the glyph
+        let solo = shared_part(1, Palette::default(), &config);
+        assert!(solo.part.contains(" ⇄ "));
+        assert_eq!(solo.len, 3);
+    }
+
+    #[test]
+    fn the_shared_indicator_waits_for_the_client_list() {
+        let config = Config {
+            shared_indicator: true,
+            shared_position: Side::Left,
+            ..Default::default()
+        };
+        let listed = BarInfo {
+            clients: Some(2),
+            ..bar()
+        };
+        let parts = line_with(&listed, tabs(2, 0, &config), 0, 80, &config);
+        assert!(parts[0].part.contains(" ⇄ 2 "));
+        let parts = line_with(&bar(), tabs(2, 0, &config), 0, 80, &config);
+        assert!(!parts.iter().any(|part| part.part.contains('⇄')));
+    }
+
+    #[test]
+    fn tabs_that_fit_exactly_need_no_indicators() {
+        let config = Config::default();
+        let tabs_width = get_current_title_len(&tabs(5, 2, &config));
+        let parts = line(&bar(), tabs(5, 2, &config), 2, tabs_width);
+        assert_eq!(tab_indices(&parts), vec![0, 1, 2, 3, 4]);
+        assert!(!parts.iter().any(|part| part.kind == PartKind::Overflow));
+        assert_eq!(get_current_title_len(&parts), tabs_width);
+    }
+
+    #[test]
+    fn one_column_short_brings_the_indicators_back() {
+        let config = Config::default();
+        let tabs_width = get_current_title_len(&tabs(5, 2, &config));
+        let parts = line(&bar(), tabs(5, 2, &config), 2, tabs_width - 1);
+        assert!(parts.iter().any(|part| part.kind == PartKind::Overflow));
+        assert!(tab_indices(&parts).len() < 5);
+        assert!(tab_indices(&parts).contains(&2));
+        assert!(get_current_title_len(&parts) < tabs_width);
+    }
+
+    #[test]
+    fn mode_hints_only_take_columns_no_tab_wanted() {
+        let config = Config::default();
+        let tabs_width = get_current_title_len(&tabs(3, 0, &config));
+        let hints = vec!["^g lock".to_string(), "^p pane".to_string()];
+        let bar = BarInfo {
+            mode_hints: &hints,
+            ..bar()
+        };
+        // every tab fits exactly, so there is no room left for hints
+        let parts = line(&bar, tabs(3, 0, &config), 0, tabs_width);
+        assert_eq!(tab_indices(&parts), vec![0, 1, 2]);
+        assert!(!parts.iter().any(|part| part.part.contains("lock")));
+        assert_eq!(get_current_title_len(&parts), tabs_width);
+    }
+
+    #[test]
+    fn mode_hints_fill_what_is_left() {
+        let config = Config::default();
+        let tabs_width = get_current_title_len(&tabs(3, 0, &config));
+        let hints = vec!["^g lock".to_string(), "^p pane".to_string()];
+        let bar = BarInfo {
+            mode_hints: &hints,
+            ..bar()
+        };
+        // room for the first hint only, " ^g lock " is nine columns
+        let parts = line(&bar, tabs(3, 0, &config), 0, tabs_width + 10);
+        assert_eq!(tab_indices(&parts), vec![0, 1, 2]);
+        assert!(parts.iter().any(|part| part.part.contains("lock")));
+        assert!(!parts.iter().any(|part| part.part.contains("pane")));
+        assert_eq!(get_current_title_len(&parts), tabs_width + 10);
+    }
+
+    #[test]
+    fn mode_hints_never_hide_an_inactive_tab() {
+        let config = Config::default();
+        let tabs_width = get_current_title_len(&tabs(3, 0, &config));
+        let hints = vec!["^g lock".to_string()];
+        let without = line(&bar(), tabs(3, 0, &config), 0, tabs_width - 1);
+        let bar = BarInfo {
+            mode_hints: &hints,
+            ..bar()
+        };
+        let with = line(&bar, tabs(3, 0, &config), 0, tabs_width - 1);
+        assert_eq!(tab_indices(&with), tab_indices(&without));
+    }
+
+    #[test]
+    fn right_aligned_tabs_end_at_the_edge_and_keep_their_clicks() {
+        let config = Config {
+            align: Side::Right,
+            ..Default::default()
+        };
+        let parts = line_with(&bar(), tabs(3, 0, &config), 0, 80, &config);
+        assert_eq!(parts[0].kind, PartKind::Filler);
+        assert_eq!(get_current_title_len(&parts), 80);
+        let last = parts.last().unwrap();
+        assert_eq!(last.tab_index, Some(2));
+        let clicked = get_clicked_line_part(&parts, 79).and_then(|part| part.tab_index);
+        assert_eq!(clicked, Some(2));
+    }
+
+    #[test]
+    fn a_pinned_session_stays_left_of_the_right_align_filler() {
+        let config = Config {
+            align: Side::Right,
+            pin_session_left: true,
+            session_width: 8,
+            ..Default::default()
+        };
+        let bar = BarInfo {
+            session_name: Some("work"),
+            ..bar()
+        };
+        let parts = line_with(&bar, tabs(3, 0, &config), 0, 80, &config);
+        assert_eq!(parts[0].kind, PartKind::SessionName);
+        assert_eq!(parts[1].kind, PartKind::Filler);
+        assert_eq!(get_current_title_len(&parts), 80);
+        assert_eq!(
+            get_clicked_line_part(&parts, 3).map(|part| part.kind),
+            Some(PartKind::SessionName)
+        );
+    }
+
+    #[test]
+    fn padding_goes_inside_a_pinned_session() {
+        let config = Config {
+            left_padding: 2,
+            right_padding: 3,
+            pin_session_left: true,
+            session_width: 8,
+            ..Default::default()
+        };
+        let bar = BarInfo {
+            session_name: Some("work"),
+            ..bar()
+        };
+        let all_tabs = tabs(3, 0, &config);
+        let first_width = all_tabs[0].len;
+        let parts = line_with(&bar, all_tabs, 0, 80, &config);
+        assert_eq!(parts[0].kind, PartKind::SessionName);
+        assert_eq!((parts[1].kind, parts[1].len), (PartKind::Static, 2));
+        assert_eq!(parts.last().map(|part| part.len), Some(3));
+        assert_eq!(get_current_title_len(&parts), 80);
+        // the first tab starts after the session and the padding
+        assert_eq!(parts[2].tab_index, Some(0));
+        assert_eq!(get_tab_to_focus(&parts, 1, 10 + first_width), Some(2));
+    }
+
+    #[test]
+    fn every_row_is_padded_to_the_bar_width() {
+        let config = Config {
+            left_padding: 2,
+            right_padding: 3,
+            ..Default::default()
+        };
+        let palette = Palette::default();
+        let rows = [
+            header_line(
+                Some("work"),
+                InputMode::Normal,
+                padded_cols(40, &config),
+                palette,
+                &config,
+            ),
+            status_line(1, 3, Some("vim"), padded_cols(40, &config), palette),
+            placeholder_line("no tabs", None, padded_cols(40, &config), palette),
+        ];
+        for row in rows {
+            let row = pad_line(row, false, 40, palette, &config);
+            assert_eq!(row[0].len, 2);
+            assert_eq!(row.last().map(|part| part.len), Some(3));
+            assert!(get_current_title_len(&row) <= 40);
+        }
+        // a row narrower than its paddings is all padding
+        let row = pad_line(
+            minimal_line(4, 3, padded_cols(4, &config), palette),
+            false,
+            4,
+            palette,
+            &config,
+        );
+        assert_eq!(get_current_title_len(&row), 4);
+    }
+
+    #[test]
+    fn a_roomy_bar_has_a_part_per_tab_and_a_filler() {
+        let config = Config::default();
+        let tabs_width = get_current_title_len(&tabs(4, 1, &config));
+        let parts = line(&bar(), tabs(4, 1, &config), 1, 100);
+        assert_eq!(parts.len(), 5);
+        assert_eq!(tab_indices(&parts), vec![0, 1, 2, 3]);
+        assert_eq!(parts[4].kind, PartKind::Filler);
+        assert_eq!(parts[4].len, 100 - tabs_width);
+        assert_eq!(get_current_title_len(&parts), 100);
+    }
+
+    #[test]
+    fn a_narrow_bar_never_draws_past_its_columns() {
+        let config = Config::default();
+        for cols in [20, 33, 47, 61] {
+            let parts = line(&bar(), tabs(12, 6, &config), 6, cols);
+            assert!(tab_indices(&parts).contains(&6));
+            assert!(get_current_title_len(&parts) <= cols);
+        }
+    }
+
+    #[test]
+    fn truecolor_parameters_become_256_colors() {
+        assert_eq!(
+            eight_bit_colors("\u{1b}[1;38;2;255;0;0;48;2;0;0;0m 1 work \u{1b}[0m"),
+            "\u{1b}[1;38;5;196;48;5;16m 1 work \u{1b}[0m"
+        );
+        // eight bit colors and other parameters are kept as they are
+        assert_eq!(
+            eight_bit_colors("\u{1b}[48;5;236m\u{1b}[0K"),
+            "\u{1b}[48;5;236m\u{1b}[0K"
+        );
+    }
+
+    #[test]
+    fn an_rgb_palette_line_has_no_truecolor_left() {
+        let palette = Palette {
+            fg: PaletteColor::Rgb((220, 220, 220)),
+            bg: PaletteColor::Rgb((40, 40, 40)),
+            black: PaletteColor::Rgb((0, 0, 0)),
+            yellow: PaletteColor::Rgb((250, 200, 0)),
+            ..Default::default()
+        };
+        let config = Config::default();
+        let parts: Vec<LinePart> = (0..3)
+            .map(|position| {
+                let tab = tab_info(position, 0);
+                tab_style(
+                    tab.name.clone(),
+                    &tab,
+                    palette,
+                    &config,
+                    None,
+                    InputMode::Normal,
+                    TabMarks::default(),
+                )
+            })
+            .collect();
+        let line: String = parts.iter().map(|part| part.part.as_str()).collect();
+        assert!(line.contains(";2;"));
+        let converted = eight_bit_colors(&line);
+        assert!(!converted.contains("38;2;") && !converted.contains("48;2;"));
+        assert!(converted.contains("tab 2"));
+    }
+
+    #[test]
+    fn a_three_column_bar_shows_only_the_active_number() {
+        let parts = minimal_line(4, 3, 3, Palette::default());
+        assert_eq!(parts.len(), 2);
+        assert!(parts[0].part.contains(" 4 "));
+        assert_eq!(parts[0].tab_index, Some(3));
+        assert_eq!((parts[0].len, parts[1].len), (3, 0));
+        // a longer number is cut, the rest of a wider bar is filled
+        let parts = minimal_line(128, 0, 3, Palette::default());
+        assert!(parts[0].part.contains(" 12") && !parts[0].part.contains("128"));
+        let parts = minimal_line(4, 3, 8, Palette::default());
+        assert_eq!(get_current_title_len(&parts), 8);
+    }
 }
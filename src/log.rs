@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::config::LogLevel;
+
+// set once in load, the session helpers log without a config at hand
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Error as u8);
+
+pub(crate) fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub(crate) fn enabled(level: LogLevel) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+// conditions that shouldn't happen, shown unless log_level is off
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::config::LogLevel::Error) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+// expected but noisy, only with log_level debug
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        if $crate::log::enabled($crate::config::LogLevel::Debug) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use {log_debug, log_error};
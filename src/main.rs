@@ -1,30 +1,150 @@
+mod config;
+mod kdl;
 mod line;
+mod log;
+mod session;
 mod tab;
 
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::cell::RefCell;
 use std::cmp::{max, min};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryInto;
 use std::fs;
-use std::io::{BufReader, BufWriter};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use tab::get_tab_to_focus;
+use tab::{
+    close_tab, closed_tabs, get_clicked_line_part, get_clicked_tab, get_overflow_direction,
+    get_scroll_step, get_tab_to_close, get_tab_to_focus, get_tab_to_rename, landing_focus,
+    name_new_tab, next_tab_index, output_hints, output_level, pane_summary, panes_to_refocus,
+    parse_tab_progress, position_after_move, progress_bar, scroll_target, tab_pane_count,
+    tab_runs_command, tabs_to_resync, tabs_with_activity, total_pane_count, update_tab_history,
+};
 use zellij_tile::prelude::*;
+use zellij_tile_utils::style;
 
-use crate::line::tab_line;
-use crate::tab::tab_style;
+use crate::config::{
+    ActiveTabClick, ActivityCue, CacheFormat, Config, EmptyClick, NameClick, RightClickAction,
+    ScrollAction,
+};
+use crate::kdl::{layout_from_kdl, layout_to_kdl};
+use crate::line::{
+    debug_overlay, debug_overlay_width, eight_bit_colors, format_clock, grid_line, header_line,
+    minimal_line, pad_line, padded_cols, placeholder_line, status_line, tab_line, BarInfo,
+    MIN_BAR_COLS,
+};
+use crate::log::{log_debug, log_error};
+use crate::session::{
+    first_attach, layout_cache_file, layout_kdl_file, next_session, previous_session,
+    read_attached_clients, read_last_session, read_session_history, read_tab_aliases,
+    session_position, startup_session, write_atomically, write_attached_clients,
+    write_last_session, write_session_history, write_tab_aliases,
+};
+use crate::tab::{
+    format_tab, marker_width, number_region, shows_sync, tab_label, tab_padding, tab_style,
+    PaneSummary, TabMarks,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PartKind {
+    #[default]
+    Static,
+    Tab,
+    Overflow,
+    Breadcrumb,
+    Filler,
+    Sessions,
+    SessionName,
+    NewTab,
+}
 
 #[derive(Debug, Default)]
 pub struct LinePart {
     part: String,
     len: usize,
     tab_index: Option<usize>,
+    kind: PartKind,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 struct ClientLayout {
     tab_idx: usize,
     pane: (u32, bool),
+    // missing in files older than version 2, which only had tiled focus
+    #[serde(default)]
+    pane_floating: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    synced_tabs: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    floating: Vec<FloatingGeometry>,
+    // the paged tab window, added in version 3
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    window_start: Option<usize>,
+    // the focused pane of every tab by position, added in version 4 and only
+    // written with restore_tab_panes, `pane` is used for the focused tab without it
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    tab_panes: BTreeMap<usize, (u32, bool)>,
+    // unix seconds of the dump, older entries count from when they were read
+    #[serde(default)]
+    saved_at: u64,
+    // unix seconds of the switch that brought this client here, set by the bar it
+    // switched from and cleared by the bar that restores the layout, a reader that
+    // doesn't know it ignores it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    switched_at: Option<u64>,
+}
+
+impl ClientLayout {
+    // the pane a switch lands on, the focused tab's entry of tab_panes when those
+    // were written
+    fn landing_pane(&self) -> (u32, bool) {
+        self.tab_panes
+            .get(&self.tab_idx)
+            .copied()
+            .unwrap_or(self.pane)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct FloatingGeometry {
+    pane: (u32, bool),
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+// what a layout cache file holds, files written before the version was added
+// hold the bare client map and are read as version 0
+const LAYOUT_CACHE_VERSION: u32 = 4;
+// zellij doesn't tell plugins when a client detaches, so an entry no client has
+// dumped for this long is dropped, a client that only moved to another session
+// keeps its entry until then
+const LAYOUT_CACHE_TTL: u64 = 7 * 24 * 60 * 60;
+// a switch that never arrived leaves its marker behind, a bar loaded this long
+// after it restores nothing
+const SWITCH_MARKER_TTL: u64 = 60;
+// how long a tab armed by confirm_tab_close waits for the second right click
+const CLOSE_CONFIRM: Duration = Duration::from_secs(3);
+
+#[derive(Serialize, Deserialize, Debug)]
+struct LayoutCacheFile {
+    version: u32,
+    clients: BTreeMap<u32, ClientLayout>,
+}
+
+// where a pending session switch goes
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+enum SwitchTarget {
+    #[default]
+    Next,
+    Previous,
+    Named(String),
+    // the session the client came from, see session_history
+    Back,
 }
 
 #[derive(Default)]
@@ -34,23 +154,93 @@ struct State {
     active_tab_idx: usize,
     mode_info: ModeInfo,
     tab_line: Vec<LinePart>,
+    // the bar size the lines were last built for, cleared by every event
+    rendered_size: Option<(usize, usize)>,
+    output_buffer: String,
+    // the rows above and below the tabs of a multi-row bar, empty on a single row
+    header_line: Vec<LinePart>,
+    status_line: Vec<LinePart>,
     next_session: Option<String>,
+    prev_session: Option<String>,
     clients: Vec<ClientInfo>,
     switch_session_event_source_pid: Option<u32>,
     current_session: String,
     pid: u32,
+    config: Config,
+    tab_history: Vec<usize>,
+    hover_col: Option<usize>,
+    // the tab under the pointer, only tracked with enable_hover
+    hovered_tab: Option<usize>,
+    // set when the bar opened a new tab that still needs to be named
+    pending_new_tab: bool,
+    last_empty_click: Option<Instant>,
+    // when the last scroll step was taken, see scroll_debounce_ms
+    last_scroll: Option<Instant>,
+    // the tab a right click armed for closing and until when a second one closes it
+    armed_close: Option<(usize, Instant)>,
+    // position of the tab a drag started on
+    drag_source: Option<usize>,
+    // advanced on every timer event, animated segments derive their frame from it
+    tick: u64,
+    timer_running: bool,
+    sessions: Vec<SessionInfo>,
+    session_position: Option<(usize, usize)>,
+    // positions of background tabs that changed since they were last focused
+    tab_activity: BTreeSet<usize>,
+    flash_ticks: u32,
+    pending_bell: bool,
+    // zellij doesn't tell plugins when the terminal loses focus, a client_focus
+    // pipe from the terminal's focus hook does
+    client_unfocused: bool,
+    startup_checked: bool,
+    // a click on the bar asked to switch sessions, the clicking client is the current one
+    switch_session_requested: bool,
+    // set by the dump_layout pipe until the client list says which client this is
+    dump_layout_requested: bool,
+    // get_layout pipes waiting for the client list, with the cli pipe to answer on
+    layout_queries: Vec<Option<String>>,
+    switch_session_target: SwitchTarget,
+    // set by the freeze pipe, switch requests are dropped until unfreeze
+    frozen: bool,
+    window_start: Option<usize>,
+    // aliases set with the alias_tab pipe for the current session
+    tab_aliases: BTreeMap<String, String>,
+    // layouts of the current session not yet written to the cache file, see cache_flush_interval
+    layout_cache: Option<BTreeMap<u32, ClientLayout>>,
+    layout_reads: RefCell<BTreeMap<String, BTreeMap<u32, ClientLayout>>>,
+    flush_due_tick: Option<u64>,
+    // focused tab position and pane of the last layout dump
+    last_dump: Option<(usize, (u32, bool))>,
+    // where the number sits within each rendered tab, only kept while names can be clicked
+    number_regions: BTreeMap<usize, (usize, usize)>,
+    // output hints of background tabs since their last visit, see output_hints
+    tab_output: BTreeMap<usize, usize>,
+    // percentages set with the set_tab_progress pipe, by tab position
+    tab_progress: BTreeMap<usize, u8>,
+    pane_summary: Vec<PaneSummary>,
+    // what the clock showed on the last tick
+    clock_text: String,
+    // this bar already looked for a layout left by a switch, see take_switch_layout
+    switch_checked: bool,
+    // the pane this bar runs in, tells which tab it belongs to
+    plugin_id: u32,
 }
 
 register_plugin!(State);
 
 trait SwitchSession {
     fn try_switch_session(&mut self) -> ();
-    fn dump_layout_to_cache(&self) -> ();
+    fn dump_layout_to_cache(&mut self) -> ();
+    fn flush_layout_cache(&mut self) -> ();
     fn get_session_layout_info(&self, session_name: &str) -> BTreeMap<u32, ClientLayout>;
 }
 
 impl ZellijPlugin for State {
-    fn load(&mut self, _configuration: BTreeMap<String, String>) {
+    fn load(&mut self, configuration: BTreeMap<String, String>) {
+        self.config = Config::from_configuration(&configuration);
+        self.plugin_id = get_plugin_ids().plugin_id;
+        log::set_level(self.config.log_level);
+        self.frozen = self.config.frozen;
         set_selectable(false);
         request_permission(&[
             PermissionType::ReadApplicationState,
@@ -66,10 +256,35 @@ impl ZellijPlugin for State {
             EventType::SessionUpdate,
             EventType::ListClients,
         ]);
+        if self.config.needs_timer() {
+            subscribe(&[EventType::Timer]);
+        }
+        if self.config.cache_flush_interval > 0.0 {
+            subscribe(&[EventType::BeforeClose]);
+        }
+        if self.config.drag_reorder {
+            subscribe(&[EventType::RunCommandResult]);
+        }
+        if self.config.show_clock {
+            self.clock_text = self.clock();
+        }
+        self.schedule_tick();
+        // tells take_switch_layout which client this bar belongs to
+        list_clients();
     }
 
     fn update(&mut self, event: Event) -> bool {
         let mut should_render = false;
+        self.rendered_size = None;
+        // any other click or scroll on the bar disarms a tab armed for closing
+        let armed_close = match &event {
+            Event::Mouse(Mouse::Hover(..)) => None,
+            Event::Mouse(_) => self.armed_close.take(),
+            _ => None,
+        };
+        if armed_close.is_some() {
+            should_render = true;
+        }
 
         match event {
             Event::ModeUpdate(mode_info) => {
@@ -85,112 +300,576 @@ impl ZellijPlugin for State {
                     if self.active_tab_idx != active_tab_idx || self.tabs != tabs {
                         should_render = true;
                     }
+                    self.follow_active_tab(active_tab_idx);
+                    update_tab_history(
+                        &mut self.tab_history,
+                        self.active_tab_idx,
+                        active_tab_idx,
+                        &closed_tabs(&self.tabs, &tabs),
+                        tabs.len(),
+                        self.config.breadcrumb_length,
+                    );
+                    if self.pending_new_tab && tabs.len() > self.tabs.len() {
+                        self.pending_new_tab = false;
+                        name_new_tab(active_tab_idx, &self.config);
+                    }
                     self.active_tab_idx = active_tab_idx;
+                    self.tab_activity
+                        .retain(|position| *position != active_tab_index && *position < tabs.len());
+                    self.tab_output.retain(|position, _| {
+                        *position != active_tab_index && *position < tabs.len()
+                    });
+                    self.tab_progress
+                        .retain(|position, _| *position < tabs.len());
                     self.tabs = tabs;
+                    should_render |= self.restore_after_switch();
                 } else {
-                    eprintln!("Could not find active tab.");
+                    log_error!("Could not find active tab.");
                 }
             }
+            Event::Mouse(_) if self.is_bar_hidden() => {}
             Event::Mouse(me) => match me {
+                // the header and status rows only show information
+                Mouse::LeftClick(line, _) if !self.is_tab_row(line) => {}
+                Mouse::LeftClick(_, col)
+                    if get_clicked_line_part(&self.tab_line, col)
+                        .is_some_and(|part| part.kind == PartKind::Sessions) =>
+                {
+                    self.switch_session_requested = true;
+                    list_clients();
+                }
+                Mouse::LeftClick(_, col)
+                    if get_clicked_line_part(&self.tab_line, col)
+                        .is_some_and(|part| part.kind == PartKind::NewTab) =>
+                {
+                    self.open_new_tab();
+                    self.emit_event(json!({"action": "new_tab", "source": "click"}));
+                }
+                Mouse::LeftClick(_, col)
+                    if self.config.click_empty_new_tab != EmptyClick::Off
+                        && !get_clicked_line_part(&self.tab_line, col)
+                            .is_some_and(|part| part.kind != PartKind::Filler) =>
+                {
+                    self.click_empty_area();
+                }
+                Mouse::LeftClick(_, col)
+                    if self.config.page_on_overflow_click
+                        && get_overflow_direction(&self.tab_line, col).is_some() =>
+                {
+                    if let Some(forward) = get_overflow_direction(&self.tab_line, col) {
+                        self.page_window(forward);
+                        should_render = true;
+                    }
+                }
+                Mouse::LeftClick(_, col)
+                    if self.config.session_name_click
+                        && get_clicked_line_part(&self.tab_line, col)
+                            .is_some_and(|part| part.kind == PartKind::SessionName) =>
+                {
+                    start_or_reload_plugin("zellij:session-manager");
+                    self.emit_event(json!({"action": "session_manager", "source": "click"}));
+                }
                 Mouse::LeftClick(_, col) => {
-                    let tab_to_focus = get_tab_to_focus(&self.tab_line, self.active_tab_idx, col);
-                    if let Some(idx) = tab_to_focus {
+                    if self.config.drag_reorder {
+                        self.drag_source = get_clicked_tab(&self.tab_line, col);
+                    }
+                    if let Some(idx) = get_tab_to_close(&self.tab_line, col, &self.config) {
+                        close_tab(idx, self.active_tab_idx());
+                        self.emit_event(
+                            json!({"action": "close_tab", "tab": idx, "source": "click"}),
+                        );
+                    } else if let Some(idx) =
+                        get_tab_to_rename(&self.tab_line, col, &self.number_regions)
+                    {
+                        switch_tab_to(idx.try_into().unwrap());
+                        switch_to_input_mode(&InputMode::RenameTab);
+                        self.emit_event(
+                            json!({"action": "rename_tab", "tab": idx, "source": "click"}),
+                        );
+                    } else if let Some(idx) =
+                        get_tab_to_focus(&self.tab_line, self.active_tab_idx(), col)
+                    {
                         switch_tab_to(idx.try_into().unwrap());
+                        self.emit_event(focus_tab_event(idx, "click"));
+                    } else if get_clicked_tab(&self.tab_line, col)
+                        .is_some_and(|position| position + 1 == self.active_tab_idx())
+                    {
+                        self.click_active_tab();
+                    }
+                }
+                Mouse::RightClick(line, _) if !self.is_tab_row(line) => {}
+                Mouse::RightClick(_, col) => {
+                    if let Some(position) = get_clicked_tab(&self.tab_line, col) {
+                        // tabs are indexed starting from 1 so we need to add 1
+                        let idx = position + 1;
+                        match self.config.right_click_action {
+                            RightClickAction::Close
+                                if self.config.confirm_tab_close
+                                    && !armed_close.is_some_and(|(armed, deadline)| {
+                                        armed == position && Instant::now() <= deadline
+                                    }) =>
+                            {
+                                self.armed_close = Some((position, Instant::now() + CLOSE_CONFIRM));
+                                should_render = true;
+                                self.schedule_tick();
+                            }
+                            RightClickAction::Close => {
+                                close_tab(idx, self.active_tab_idx());
+                                self.emit_event(
+                                    json!({"action": "close_tab", "tab": idx, "source": "right_click"}),
+                                );
+                            }
+                            // sync is toggled on the focused tab, so the tab is focused first
+                            RightClickAction::ToggleSync => {
+                                switch_tab_to(idx as u32);
+                                toggle_active_tab_sync();
+                                self.emit_event(
+                                    json!({"action": "toggle_sync", "tab": idx, "source": "right_click"}),
+                                );
+                            }
+                            RightClickAction::Nothing => {}
+                        }
+                    }
+                }
+                Mouse::Release(_, col) => {
+                    let source = self.drag_source.take();
+                    let target = get_clicked_tab(&self.tab_line, col);
+                    if let (Some(source), Some(target)) = (source, target) {
+                        if source != target {
+                            self.move_tab(source, target);
+                        }
                     }
                 }
                 Mouse::ScrollUp(_) => {
-                    switch_tab_to(min(self.active_tab_idx + 1, self.tabs.len()) as u32);
+                    should_render = self.scroll(!self.config.invert_scroll);
                 }
                 Mouse::ScrollDown(_) => {
-                    switch_tab_to(max(self.active_tab_idx.saturating_sub(1), 1) as u32);
+                    should_render = self.scroll(self.config.invert_scroll);
+                }
+                Mouse::Hover(line, col) => {
+                    self.hover_col = Some(col);
+                    // zellij sends nothing when the pointer leaves the bar, moving
+                    // off the tab row is the closest there is
+                    if self.config.enable_hover {
+                        let hovered = Some(col)
+                            .filter(|_| self.is_tab_row(line))
+                            .and_then(|col| get_clicked_tab(&self.tab_line, col));
+                        if hovered != self.hovered_tab {
+                            self.hovered_tab = hovered;
+                            should_render = true;
+                        }
+                    }
                 }
                 _ => {}
             },
             Event::SessionUpdate(sessions, _) => {
+                self.layout_reads.borrow_mut().clear();
                 let mut all_sessions: Vec<SessionInfo> =
                     sessions.into_iter().map(|item| item).collect();
                 all_sessions.sort_by(|item1, item2| item1.name.cmp(&item2.name));
-                let current_session_index = all_sessions
-                    .iter()
-                    .position(|item| item.is_current_session)
-                    .unwrap();
+                // the list can arrive without the current session while a client
+                // detaches or reattaches, the next update will have it again, and a
+                // name listed twice resolves to the entry this bar already knows
+                let Some(current_session_index) =
+                    current_session_index(&all_sessions, &self.current_session)
+                else {
+                    log_error!("Could not find current session.");
+                    return should_render;
+                };
 
-                self.current_session = all_sessions[current_session_index].name.clone();
+                if self.current_session != all_sessions[current_session_index].name {
+                    self.current_session = all_sessions[current_session_index].name.clone();
+                    self.tab_aliases =
+                        read_tab_aliases(&self.config.cache_dir, &self.current_session);
+                    should_render = true;
+                }
+                should_render |= self.restore_after_switch();
 
-                if all_sessions.len() > 1 {
-                    self.next_session = all_sessions
-                        .remove((current_session_index + 1) % all_sessions.len())
-                        .name
-                        .into();
+                let session_names: Vec<&str> =
+                    all_sessions.iter().map(|item| item.name.as_str()).collect();
+                self.next_session = next_session(
+                    &session_names,
+                    &self.current_session,
+                    &self.config.session_order,
+                );
+                self.prev_session = previous_session(
+                    &session_names,
+                    &self.current_session,
+                    &self.config.session_order,
+                );
+                let position = session_position(
+                    &session_names,
+                    &self.current_session,
+                    &self.config.session_order,
+                );
+                if self.config.session_count && self.session_position != position {
+                    should_render = true;
                 }
+                self.session_position = position;
+                self.sessions = all_sessions;
+                self.restore_last_session();
             }
             Event::PaneUpdate(panes) => {
+                let summary = pane_summary(&panes, self.config.draws_pane_title());
+                if self.config.renders_on_pane_update() && summary != self.pane_summary {
+                    should_render = true;
+                }
+                self.pane_summary = summary;
+                let active_position = self.active_tab_idx().saturating_sub(1);
+                if self.record_activity(&panes) {
+                    should_render |= self.activity_cue() || self.config.activity_indicator;
+                }
+                if self.config.output_indicator {
+                    for (position, hints) in output_hints(&self.panes, &panes) {
+                        if position != active_position {
+                            *self.tab_output.entry(position).or_default() += hints;
+                            should_render = true;
+                        }
+                    }
+                }
                 self.panes = panes;
+                should_render |= self.restore_after_switch();
             }
             Event::ListClients(clients) => {
+                if self.config.shared_indicator && self.clients.len() != clients.len() {
+                    should_render = true;
+                }
                 self.clients = clients;
+                should_render |= self.restore_after_switch();
+                self.restore_last_session();
+                if std::mem::take(&mut self.dump_layout_requested) {
+                    self.dump_layout_now();
+                }
+                for pipe_id in std::mem::take(&mut self.layout_queries) {
+                    self.answer_layout_query(pipe_id);
+                }
                 self.try_switch_session();
             }
+            Event::Timer(_) => {
+                should_render = self.advance_tick();
+                if self.config.shared_indicator {
+                    list_clients();
+                }
+                if self.config.show_clock {
+                    let clock = self.clock();
+                    if clock != self.clock_text {
+                        self.clock_text = clock;
+                        should_render = true;
+                    }
+                }
+                if self
+                    .armed_close
+                    .is_some_and(|(_, deadline)| Instant::now() > deadline)
+                {
+                    self.armed_close = None;
+                    should_render = true;
+                }
+                if self.flush_due_tick.is_some_and(|due| self.tick >= due) {
+                    self.flush_layout_cache();
+                }
+                self.schedule_tick();
+            }
+            Event::BeforeClose => {
+                self.flush_layout_cache();
+            }
+            Event::RunCommandResult(exit_code, _, stderr, context) => {
+                self.tab_moved(exit_code, &stderr, &context);
+            }
             _ => {
-                eprintln!("Got unrecognized event: {:?}", event);
+                log_debug!("Got unrecognized event: {:?}", event);
             }
         };
         should_render
     }
 
-    fn render(&mut self, _rows: usize, cols: usize) {
+    fn render(&mut self, rows: usize, cols: usize) {
+        if self.pending_bell {
+            print!("\u{7}");
+            self.pending_bell = false;
+        }
+        // zellij also renders the bar when other panes change, nothing it shows
+        // can have changed without an event or a resize in between
+        if self.rendered_size == Some((rows, cols)) {
+            self.print_tab_line();
+            return;
+        }
+        self.rendered_size = Some((rows, cols));
+        self.header_line.clear();
+        self.status_line.clear();
         if self.tabs.is_empty() {
+            // without a placeholder the bar is still filled, so nothing stale is left on it
+            self.tab_line = match &self.config.empty_placeholder {
+                Some(placeholder) => self.pad_row(
+                    placeholder_line(
+                        placeholder,
+                        self.mode_info.session_name.as_deref(),
+                        padded_cols(cols.saturating_sub(1), &self.config),
+                        self.mode_info.style.colors,
+                    ),
+                    cols.saturating_sub(1),
+                ),
+                None => Vec::new(),
+            };
+            self.print_tab_line();
+            return;
+        }
+        if self.is_bar_hidden() {
+            self.tab_line.clear();
+            self.print_tab_line();
             return;
         }
 
+        let cols = cols.saturating_sub(1);
+        if cols < MIN_BAR_COLS {
+            if let Some((index, tab)) = self.tabs.iter().enumerate().find(|(_, t)| t.active) {
+                let line = minimal_line(
+                    index + 1,
+                    tab.position,
+                    padded_cols(cols, &self.config),
+                    self.mode_info.style.colors,
+                );
+                self.tab_line = self.pad_row(line, cols);
+            } else {
+                self.tab_line.clear();
+            }
+            self.print_tab_line();
+            return;
+        }
+        let debug_width = if self.config.debug {
+            debug_overlay_width(cols)
+        } else {
+            0
+        };
+        let slot_width = Some(self.config.grid_slots)
+            .filter(|slots| *slots > 0)
+            .map(|slots| padded_cols(cols.saturating_sub(debug_width), &self.config) / slots)
+            .filter(|slot_width| *slot_width >= 3);
+
+        self.number_regions.clear();
+        let all_synced = self.all_tabs_synced();
         let mut all_tabs: Vec<LinePart> = vec![];
         let mut active_tab_index = 0;
         for (index, t) in &mut self.tabs.iter().enumerate() {
             let mut tabname = t.name.clone();
             if t.active && self.mode_info.mode == InputMode::RenameTab {
+                // the real name is the one being edited
                 if tabname.is_empty() {
-                    tabname = String::from("Enter name...");
+                    tabname = self
+                        .config
+                        .rename_placeholder
+                        .clone()
+                        .unwrap_or_else(|| String::from("Enter name..."));
                 }
                 active_tab_index = t.position;
-            } else if t.active {
-                active_tab_index = t.position;
+            } else {
+                if let Some(alias) = self.tab_alias(&t.name) {
+                    tabname = alias.to_string();
+                }
+                if t.active {
+                    active_tab_index = t.position;
+                }
+            }
+            let hints = self
+                .tab_output
+                .get(&t.position)
+                .copied()
+                .unwrap_or_default();
+            let level = output_level(hints, self.config.output_levels.len());
+            if level > 0 {
+                tabname.push(' ');
+                tabname.push(self.config.output_levels[level - 1]);
+            }
+            if self.config.show_running_marker && tab_runs_command(&self.panes, t.position) {
+                tabname.push(' ');
+                tabname.push_str(self.config.running_marker.get());
+            }
+            if self.config.show_pane_count {
+                let pane_count = tab_pane_count(&self.panes, t.position);
+                tabname.push_str(&format!(" [{}]", pane_count));
             }
+            if let Some(percent) = self.tab_progress.get(&t.position) {
+                tabname.push(' ');
+                tabname.push_str(&progress_bar(*percent, self.config.progress_width));
+            }
+            let unread = self.config.activity_indicator
+                && !t.active
+                && self.tab_activity.contains(&t.position);
+            let (label, (start, end)) = match &self.config.tab_format {
+                Some(template) => format_tab(
+                    template,
+                    index + 1,
+                    &tabname,
+                    tab_pane_count(&self.panes, t.position),
+                    t,
+                    shows_sync(t, all_synced, &self.config),
+                    &self.config,
+                ),
+                None => (
+                    tab_label(index + 1, self.tabs.len(), &tabname, &self.config),
+                    number_region(index + 1, self.tabs.len(), &tabname, &self.config),
+                ),
+            };
             let tab = tab_style(
-                (index + 1).to_string() + " " + tabname.as_ref(),
+                label,
                 t,
                 self.mode_info.style.colors,
+                &self.config,
+                slot_width,
+                self.mode_info.mode,
+                TabMarks {
+                    all_synced,
+                    unread,
+                    hovered: self.hovered_tab == Some(t.position),
+                    armed: self
+                        .armed_close
+                        .is_some_and(|(position, _)| position == t.position),
+                    unfocused: self.config.dim_when_unfocused && self.client_unfocused,
+                },
             );
+            // a grid slot may center or cut the label, so its number can't be located
+            if self.config.name_click == NameClick::Rename && slot_width.is_none() {
+                let mut padding = tab_padding(t, &self.config);
+                if unread {
+                    padding += marker_width(self.config.activity_glyph.get());
+                }
+                self.number_regions
+                    .insert(t.position, (start + padding, end + padding));
+            }
             all_tabs.push(tab);
         }
-        self.tab_line = tab_line(
-            self.mode_info.session_name.as_deref(),
-            all_tabs,
-            active_tab_index,
-            cols.saturating_sub(1),
-            self.mode_info.style.colors,
-            self.mode_info.capabilities,
-            self.mode_info.mode,
-        );
-        let output = self
-            .tab_line
-            .iter()
-            .fold(String::new(), |output, part| output + &part.part);
-        let background = match self.mode_info.style.colors.theme_hue {
-            ThemeHue::Dark => self.mode_info.style.colors.black,
-            ThemeHue::Light => self.mode_info.style.colors.white,
+        if self.config.reverse_tab_order {
+            // parts keep their tab_index so clicks still focus the right tab
+            all_tabs.reverse();
+            active_tab_index = all_tabs.len().saturating_sub(active_tab_index + 1);
+        }
+        let multi_row = self.config.multi_row && rows >= 2;
+        let session_label = self.session_label();
+        let pane_title = if self.config.show_pane_title {
+            self.focused_pane_title()
+        } else {
+            None
         };
-        match background {
-            PaletteColor::Rgb((r, g, b)) => {
-                print!("{}\u{1b}[48;2;{};{};{}m\u{1b}[0K", output, r, g, b);
-            }
-            PaletteColor::EightBit(color) => {
-                print!("{}\u{1b}[48;5;{}m\u{1b}[0K", output, color);
-            }
+        self.tab_line = if let Some(slot_width) = slot_width {
+            let line = grid_line(
+                all_tabs,
+                active_tab_index,
+                self.config.grid_slots,
+                slot_width,
+                padded_cols(cols.saturating_sub(debug_width), &self.config),
+                self.mode_info.style.colors,
+            );
+            self.pad_row(line, cols.saturating_sub(debug_width))
+        } else {
+            let mode_hints = if self.config.show_mode_hints {
+                self.mode_hints()
+            } else {
+                Vec::new()
+            };
+            // a name without the delimiter is a group of its own
+            let tab_groups: Vec<String> = match &self.config.group_delimiter {
+                Some(delimiter) => self
+                    .tabs
+                    .iter()
+                    .map(|tab| match tab.name.split_once(delimiter.as_str()) {
+                        Some((group, _)) => group.to_string(),
+                        None => tab.name.clone(),
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            let bar = BarInfo {
+                session_name: session_label.as_deref().filter(|_| !multi_row),
+                mode: self.mode_info.mode,
+                tab_history: &self.tab_history,
+                pane_title: pane_title.as_deref(),
+                other_sessions: self.other_session_count(),
+                window_start: self.window_start,
+                show_mode: !multi_row,
+                pane_count: self
+                    .config
+                    .pane_count
+                    .then(|| total_pane_count(&self.panes, self.config.pane_count_plugins)),
+                tab_counter: self
+                    .config
+                    .show_tab_counter
+                    .then_some((self.active_tab_idx(), self.tabs.len())),
+                layout_dirty: self.layout_dirty(),
+                clients: Some(self.clients.len()).filter(|clients| *clients > 0),
+                mode_hints: &mode_hints,
+                tab_groups: &tab_groups,
+                clock: Some(self.clock_text.as_str())
+                    .filter(|_| self.config.show_clock && !self.clock_text.is_empty()),
+            };
+            tab_line(
+                &bar,
+                all_tabs,
+                active_tab_index,
+                cols.saturating_sub(debug_width),
+                self.mode_info.style.colors,
+                self.mode_info.capabilities,
+                &self.config,
+            )
         };
+        if multi_row {
+            let palette = self.mode_info.style.colors;
+            let line = header_line(
+                session_label.as_deref(),
+                self.mode_info.mode,
+                padded_cols(cols, &self.config),
+                palette,
+                &self.config,
+            );
+            self.header_line = self.pad_row(line, cols);
+            if rows >= 3 && self.config.status_row {
+                let focused_title =
+                    get_focused_pane(self.active_tab_idx().saturating_sub(1), &self.panes)
+                        .map(|pane| pane.title);
+                let line = status_line(
+                    self.active_tab_idx(),
+                    self.tabs.len(),
+                    focused_title.as_deref(),
+                    padded_cols(cols, &self.config),
+                    palette,
+                );
+                self.status_line = self.pad_row(line, cols);
+            }
+        }
+        // line.rs lets the terminal clip a line that is too long, this at least says by how much
+        let line_width: usize = self.tab_line.iter().map(|part| part.len).sum();
+        if line_width > cols.saturating_sub(debug_width) {
+            log_debug!(
+                "Tab line is {} columns wide, {} more than the {} available",
+                line_width,
+                line_width - cols.saturating_sub(debug_width),
+                cols.saturating_sub(debug_width)
+            );
+        }
+        if self.config.debug {
+            let overlay = debug_overlay(&self.tab_line, cols, self.mode_info.style.colors);
+            self.tab_line.push(overlay);
+        }
+        self.print_tab_line();
     }
 
     fn pipe(&mut self, pipe_msg: PipeMessage) -> bool {
-        if pipe_msg.name == "switch_session" {
+        self.rendered_size = None;
+        if matches!(
+            pipe_msg.name.as_str(),
+            "switch_session" | "switch_session_prev" | "switch_session_to" | "session_back"
+        ) {
+            self.switch_session_target = match pipe_msg.name.as_str() {
+                "switch_session_prev" => SwitchTarget::Previous,
+                "session_back" => SwitchTarget::Back,
+                "switch_session_to" => {
+                    let target = pipe_msg.payload.as_deref().map(str::trim);
+                    let Some(target) = target.filter(|name| !name.is_empty()) else {
+                        log_error!("switch_session_to needs a session name");
+                        return false;
+                    };
+                    SwitchTarget::Named(target.to_string())
+                }
+                _ => SwitchTarget::Next,
+            };
             self.switch_session_event_source_pid = match pipe_msg.source {
                 PipeSource::Keybind {
                     source_client_id: _,
@@ -199,98 +878,1945 @@ impl ZellijPlugin for State {
                 _ => None,
             };
             list_clients();
+        } else if pipe_msg.name == "freeze" || pipe_msg.name == "unfreeze" {
+            self.frozen = pipe_msg.name == "freeze";
+            return false;
+        } else if pipe_msg.name == "cycle_glyphs" {
+            self.config.cycle_glyphs();
+        } else if pipe_msg.name == "new_tab" {
+            self.open_new_tab();
+        } else if pipe_msg.name == "dump_layout" {
+            self.dump_layout_requested = true;
+            list_clients();
+            return false;
+        } else if pipe_msg.name == "get_layout" {
+            self.layout_queries.push(match &pipe_msg.source {
+                PipeSource::Cli(pipe_id) => Some(pipe_id.clone()),
+                _ => None,
+            });
+            list_clients();
+            return false;
+        } else if pipe_msg.name == "ping" {
+            let status = self.status();
+            match &pipe_msg.source {
+                PipeSource::Cli(pipe_id) => cli_pipe_output(pipe_id, &status.to_string()),
+                _ => self.emit_event(status),
+            }
+            return false;
+        } else if pipe_msg.name == "set_tab_progress" {
+            let progress = pipe_msg.payload.as_deref().and_then(parse_tab_progress);
+            match progress {
+                // tab_progress is keyed by position which starts from 0
+                Some((position, Some(percent))) => {
+                    self.tab_progress.insert(position - 1, percent);
+                }
+                Some((position, None)) => {
+                    self.tab_progress.remove(&(position - 1));
+                }
+                None => log_error!("Invalid tab progress: {:?}", pipe_msg.payload),
+            }
+        } else if pipe_msg.name == "client_focus" {
+            let unfocused = matches!(pipe_msg.payload.as_deref(), Some("unfocused" | "out"));
+            if unfocused == self.client_unfocused {
+                return false;
+            }
+            self.client_unfocused = unfocused;
+            if !unfocused && self.config.restore_last_session {
+                write_last_session(&self.config.cache_dir, &self.current_session);
+            }
+        } else if pipe_msg.name == "alias_tab" {
+            self.alias_active_tab(pipe_msg.payload.as_deref());
+        } else if pipe_msg.name == "scroll_window" {
+            let forward = !matches!(pipe_msg.payload.as_deref(), Some("left"));
+            self.scroll_window(forward);
+        } else if pipe_msg.name == "cycle_tab" {
+            let forward = !matches!(pipe_msg.payload.as_deref(), Some("previous" | "prev"));
+            let (idx, wrapped) = next_tab_index(self.active_tab_idx(), self.tabs.len(), forward);
+            if idx != self.active_tab_idx() {
+                switch_tab_to(idx as u32);
+            }
+            if self.config.cycle_tab_feedback {
+                let feedback = json!({"action": "cycle_tab", "tab": idx, "wrapped": wrapped});
+                match &pipe_msg.source {
+                    PipeSource::Cli(pipe_id) => cli_pipe_output(pipe_id, &feedback.to_string()),
+                    _ => self.emit_event(feedback),
+                }
+            }
         }
         true
     }
 }
 
-impl SwitchSession for State {
-    fn try_switch_session(&mut self) -> () {
-        if self.current_session.is_empty() {
-            return ();
-        }
+impl State {
+    // kept within the tabs the bar knows, which can be fewer than the last active
+    // tab was counted among before the TabUpdate that dropped them is handled
+    fn active_tab_idx(&self) -> usize {
+        min(self.active_tab_idx, self.tabs.len())
+    }
 
-        let plugin_pid = self
-            .clients
+    // the keys bound in the current mode with the name of the first action of each
+    fn mode_hints(&self) -> Vec<String> {
+        self.mode_info
+            .get_mode_keybinds()
             .iter()
-            .find(|client_info| client_info.is_current_client)
-            .map(|v| v.client_pid);
+            .filter_map(|(key, actions)| {
+                let action = format!("{:?}", actions.first()?);
+                let name = action.split(['(', ' ', '{']).next().unwrap_or_default();
+                Some(format!("{} {}", key, name))
+            })
+            .collect()
+    }
 
-        if plugin_pid.is_none() {
-            return ();
-        }
+    fn other_session_count(&self) -> usize {
+        self.sessions
+            .iter()
+            .filter(|session| session.name != self.current_session)
+            .count()
+    }
 
-        self.pid = plugin_pid.unwrap();
-        if self.switch_session_event_source_pid.is_some()
-            && self.pid == self.switch_session_event_source_pid.unwrap()
-        {
-            if self.next_session.is_some() {
-                self.dump_layout_to_cache();
+    // title of the focused pane in the active tab, none while it has no title
+    fn focused_pane_title(&self) -> Option<String> {
+        get_focused_pane(self.active_tab_idx().saturating_sub(1), &self.panes)
+            .map(|pane| pane.title.trim().to_string())
+            .filter(|title| !title.is_empty())
+    }
 
-                let next_session = self.next_session.as_deref().unwrap();
-                match self
-                    .get_session_layout_info(&next_session)
-                    .remove(&self.pid)
-                {
-                    Some(layout) => {
-                        switch_session_with_focus(
-                            next_session,
-                            layout.tab_idx.into(),
-                            layout.pane.into(),
-                        );
-                    }
-                    None => {
-                        switch_session(self.next_session.as_deref());
-                    }
-                }
+    fn session_label(&self) -> Option<String> {
+        if !self.config.show_session_name {
+            return None;
+        }
+        let name = self.mode_info.session_name.as_deref()?;
+        match self.session_position {
+            Some((position, total)) if self.config.session_count => {
+                Some(format!("{} ({}/{})", name, position, total))
             }
+            _ => Some(name.to_string()),
+        }
+    }
+
+    // the window is laid out around the active tab again, one moved by hand is
+    // only kept while the active tab is the same
+    fn follow_active_tab(&mut self, active_tab_idx: usize) {
+        if self.active_tab_idx != active_tab_idx || !self.config.keeps_window() {
+            self.window_start = None;
         }
+    }
 
-        self.switch_session_event_source_pid = None;
+    // moves the first visible tab by one, the window snaps back to the active
+    // tab if that would push it out of view, unless scrolling pages the window
+    fn scroll_window(&mut self, forward: bool) {
+        let tab_count = self.tabs.len();
+        let start = self.window_start.or_else(|| {
+            let first_visible = self
+                .tab_line
+                .iter()
+                .find(|part| part.kind == PartKind::Tab)?
+                .tab_index?;
+            if self.config.reverse_tab_order {
+                Some(tab_count.saturating_sub(first_visible + 1))
+            } else {
+                Some(first_visible)
+            }
+        });
+        let Some(start) = start else {
+            return;
+        };
+        self.window_start = Some(if forward {
+            min(start + 1, tab_count.saturating_sub(1))
+        } else {
+            start.saturating_sub(1)
+        });
     }
 
-    fn dump_layout_to_cache(&self) -> () {
-        let focused_tab_idx = get_focused_tab(&self.tabs).map(|tab| tab.position);
-        if focused_tab_idx.is_none() {
-            return ();
+    // scrolling up moves forward unless invert_scroll is set, returns whether the
+    // bar needs to be drawn again
+    fn scroll(&mut self, forward: bool) -> bool {
+        // one flick of the wheel sends a burst of events, only its first one steps
+        if self.config.scroll_debounce_ms > 0 {
+            let now = Instant::now();
+            let debounce = Duration::from_millis(self.config.scroll_debounce_ms);
+            if self
+                .last_scroll
+                .is_some_and(|last| now.duration_since(last) < debounce)
+            {
+                return false;
+            }
+            self.last_scroll = Some(now);
+        }
+        if self.config.scroll_action == ScrollAction::PageWindow {
+            self.scroll_window(forward);
+            return true;
+        }
+        if self.config.scroll_action == ScrollAction::SwitchSession {
+            // same as a click on the sessions segment, the client list says who scrolled
+            self.switch_session_target = if forward {
+                SwitchTarget::Next
+            } else {
+                SwitchTarget::Previous
+            };
+            self.switch_session_requested = true;
+            list_clients();
+            return false;
         }
+        let step = get_scroll_step(
+            &self.tab_line,
+            self.hover_col,
+            self.config.page_scroll_on_overflow,
+        );
+        let idx = scroll_target(
+            self.active_tab_idx(),
+            self.tabs.len(),
+            step,
+            forward,
+            self.config.wrap_tabs,
+        );
+        switch_tab_to(idx as u32);
+        self.emit_event(focus_tab_event(idx, "scroll"));
+        false
+    }
 
-        let focused_pane = get_focused_pane(focused_tab_idx.unwrap(), &self.panes);
-        if focused_pane.is_none() {
-            return ();
+    // moves the window by as many tabs as it shows
+    fn page_window(&mut self, forward: bool) {
+        let visible = self
+            .tab_line
+            .iter()
+            .filter(|part| part.kind == PartKind::Tab)
+            .count();
+        for _ in 0..visible.max(1) {
+            self.scroll_window(forward);
         }
+    }
 
-        let layout = ClientLayout {
-            tab_idx: focused_tab_idx.unwrap(),
-            pane: (
-                focused_pane.as_ref().unwrap().id,
-                focused_pane.unwrap().is_plugin,
-            ),
+    fn tab_alias(&self, name: &str) -> Option<&str> {
+        self.tab_aliases
+            .get(name)
+            .or_else(|| self.config.tab_aliases.get(name))
+            .map(String::as_str)
+    }
+
+    // an empty payload drops the alias of the active tab
+    fn alias_active_tab(&mut self, alias: Option<&str>) {
+        let Some(tab) = get_focused_tab(&self.tabs) else {
+            return;
+        };
+        match alias.map(str::trim).filter(|alias| !alias.is_empty()) {
+            Some(alias) => self.tab_aliases.insert(tab.name, alias.to_string()),
+            None => self.tab_aliases.remove(&tab.name),
         };
+        if !self.current_session.is_empty() {
+            write_tab_aliases(
+                &self.config.cache_dir,
+                &self.current_session,
+                &self.tab_aliases,
+            );
+        }
+    }
 
-        let mut layout_info = self.get_session_layout_info(&self.current_session);
-        layout_info.insert(self.pid, layout);
+    // answer to the ping pipe, fields zellij hasn't told the plugin about yet are null
+    fn status(&self) -> serde_json::Value {
+        json!({
+            "session": Some(&self.current_session).filter(|name| !name.is_empty()),
+            "tab_count": self.tabs.len(),
+            "active_tab": Some(self.active_tab_idx()).filter(|idx| *idx > 0),
+            "switch_pending": self.switch_session_requested
+                || self.switch_session_event_source_pid.is_some(),
+            "client_pid": Some(self.pid).filter(|pid| *pid > 0),
+            "frozen": self.frozen,
+        })
+    }
 
-        serde_json::to_writer_pretty(
-            BufWriter::new(
-                fs::File::create(format!("/tmp/{0}.json", self.current_session))
-                    .expect("could not open file"),
+    // plugins can't move tabs themselves, so the zellij cli focuses the dragged tab
+    // and moves it one step at a time until it is where it was dropped, the result
+    // comes back as a RunCommandResult with the positions in its context
+    fn move_tab(&self, source: usize, target: usize) {
+        let direction = if target > source { "right" } else { "left" };
+        let mut script = format!("zellij -s \"$1\" action go-to-tab {}", source + 1);
+        for _ in 0..source.abs_diff(target) {
+            script.push_str(&format!(
+                " && zellij -s \"$1\" action move-tab {}",
+                direction
+            ));
+        }
+        let context = BTreeMap::from([
+            (String::from("move_tab"), source.to_string()),
+            (String::from("to"), target.to_string()),
+            (
+                String::from("active"),
+                self.active_tab_idx().saturating_sub(1).to_string(),
             ),
-            &layout_info,
-        )
-        .unwrap();
+        ]);
+        run_command(&["sh", "-c", &script, "sh", &self.current_session], context);
     }
 
-    fn get_session_layout_info(&self, session_name: &str) -> BTreeMap<u32, ClientLayout> {
-        let file = fs::File::open(format!("/tmp/{0}.json", session_name));
-        if file.is_ok() {
-            let reader = BufReader::new(file.unwrap());
-            match serde_json::from_reader(reader) {
-                Ok(val) => val,
-                Err(_) => BTreeMap::new(),
+    // the cli left the moved tab focused, so focus goes back to the tab that was
+    // active before the drag, wherever the move put it
+    fn tab_moved(&self, exit_code: Option<i32>, stderr: &[u8], context: &BTreeMap<String, String>) {
+        let position = |key: &str| context.get(key)?.parse::<usize>().ok();
+        let (Some(source), Some(target), Some(active)) =
+            (position("move_tab"), position("to"), position("active"))
+        else {
+            return;
+        };
+        if exit_code != Some(0) {
+            log_error!(
+                "Could not move tab {} to {}: {}",
+                source + 1,
+                target + 1,
+                String::from_utf8_lossy(stderr).trim()
+            );
+            return;
+        }
+        // tabs are indexed starting from 1 so we need to add 1
+        switch_tab_to(position_after_move(active, source, target) as u32 + 1);
+        self.emit_event(
+            json!({"action": "move_tab", "tab": source + 1, "to": target + 1, "source": "drag"}),
+        );
+    }
+
+    fn open_new_tab(&mut self) {
+        self.pending_new_tab = self.config.new_tab_name.is_some() || self.config.rename_new_tab;
+        new_tab();
+    }
+
+    // a click on the tab already focused, get_tab_to_focus leaves those alone
+    fn click_active_tab(&self) {
+        let idx = self.active_tab_idx();
+        match self.config.click_active_tab_action {
+            ActiveTabClick::Nothing => {}
+            ActiveTabClick::Rename => {
+                switch_to_input_mode(&InputMode::RenameTab);
+                self.emit_event(json!({"action": "rename_tab", "tab": idx, "source": "click"}));
+            }
+            ActiveTabClick::ToggleFullscreen => {
+                toggle_focus_fullscreen();
+                self.emit_event(
+                    json!({"action": "toggle_fullscreen", "tab": idx, "source": "click"}),
+                );
+            }
+        }
+    }
+
+    // a click past the tabs, a double click only counts when both land there
+    fn click_empty_area(&mut self) {
+        const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+        let now = Instant::now();
+        let opens = match self.config.click_empty_new_tab {
+            EmptyClick::Off => false,
+            EmptyClick::Single => true,
+            EmptyClick::Double => self
+                .last_empty_click
+                .is_some_and(|last| now.duration_since(last) <= DOUBLE_CLICK),
+        };
+        if opens {
+            self.last_empty_click = None;
+            self.open_new_tab();
+            self.emit_event(json!({"action": "new_tab", "source": "click"}));
+        } else {
+            self.last_empty_click = Some(now);
+        }
+    }
+
+    // what dump_layout_to_cache would write for this client, None without a focused pane
+    fn current_layout(&self) -> Option<ClientLayout> {
+        let focused_tab_idx = get_focused_tab(&self.tabs).map(|tab| tab.position)?;
+        let focused_pane = get_focused_pane(focused_tab_idx, &self.panes)?;
+
+        Some(ClientLayout {
+            tab_idx: focused_tab_idx,
+            pane: (focused_pane.id, focused_pane.is_plugin),
+            pane_floating: focused_pane.is_floating,
+            mode: self
+                .config
+                .restore_input_mode
+                .then(|| format!("{:?}", self.mode_info.mode)),
+            synced_tabs: if self.config.restore_sync_panes {
+                self.tabs
+                    .iter()
+                    .filter(|tab| tab.is_sync_panes_active)
+                    .map(|tab| tab.position)
+                    .collect()
+            } else {
+                vec![]
+            },
+            window_start: self.window_start,
+            tab_panes: if self.config.restore_tab_panes {
+                self.tabs
+                    .iter()
+                    .filter_map(|tab| {
+                        let pane = get_focused_pane(tab.position, &self.panes)?;
+                        Some((tab.position, (pane.id, pane.is_plugin)))
+                    })
+                    .collect()
+            } else {
+                BTreeMap::new()
+            },
+            floating: if self.config.restore_floating_geometry {
+                self.panes
+                    .panes
+                    .values()
+                    .flatten()
+                    .filter(|pane| pane.is_floating)
+                    .map(|pane| FloatingGeometry {
+                        pane: (pane.id, pane.is_plugin),
+                        x: pane.pane_x,
+                        y: pane.pane_y,
+                        width: pane.pane_columns,
+                        height: pane.pane_rows,
+                    })
+                    .collect()
+            } else {
+                vec![]
+            },
+            saved_at: unix_now(),
+            switched_at: None,
+        })
+    }
+
+    // the layout shaped like a cache file holding only this client, clients is
+    // empty while the focus is not known
+    fn answer_layout_query(&self, pipe_id: Option<String>) {
+        let client = self.clients.iter().find(|client| client.is_current_client);
+        let clients: BTreeMap<u32, ClientLayout> = client
+            .and_then(|client| Some((client.client_pid, self.current_layout()?)))
+            .into_iter()
+            .collect();
+        let response = json!({
+            "session": self.current_session,
+            "version": LAYOUT_CACHE_VERSION,
+            "clients": clients,
+        });
+        match pipe_id {
+            Some(pipe_id) => cli_pipe_output(&pipe_id, &response.to_string()),
+            None => self.emit_event(response),
+        }
+    }
+
+    // a dump asked for by hand is written right away whatever the flush interval
+    fn dump_layout_now(&mut self) {
+        let Some(client) = self.clients.iter().find(|client| client.is_current_client) else {
+            return;
+        };
+        if self.current_session.is_empty() {
+            return;
+        }
+        self.pid = client.client_pid;
+        self.dump_layout_to_cache();
+        self.flush_layout_cache();
+    }
+
+    // each session runs its own copy of the plugin, so the visit history lives
+    // in the cache directory where every copy can find it
+    fn push_session_history(&self) {
+        let mut history = read_session_history(&self.config.cache_dir);
+        if history.last() != Some(&self.current_session) {
+            history.push(self.current_session.clone());
+        }
+        let excess = history
+            .len()
+            .saturating_sub(self.config.session_history_length);
+        history.drain(..excess);
+        write_session_history(&self.config.cache_dir, &history);
+    }
+
+    // the most recent session that still exists, sessions that are gone are dropped
+    fn pop_session_history(&self) -> Option<String> {
+        let mut history = read_session_history(&self.config.cache_dir);
+        let mut target = None;
+        while let Some(name) = history.pop() {
+            if name != self.current_session
+                && self.sessions.iter().any(|session| session.name == name)
+            {
+                target = Some(name);
+                break;
             }
+        }
+        write_session_history(&self.config.cache_dir, &history);
+        target
+    }
+
+    // what a layout dump made now would record
+    fn focused_layout(&self) -> Option<(usize, (u32, bool))> {
+        let tab_idx = get_focused_tab(&self.tabs)?.position;
+        let pane = get_focused_pane(tab_idx, &self.panes)?;
+        Some((tab_idx, (pane.id, pane.is_plugin)))
+    }
+
+    // the focus moved since the last dump, so a switch now wouldn't restore it
+    fn layout_dirty(&self) -> bool {
+        self.config.dirty_marker && self.last_dump != self.focused_layout()
+    }
+
+    fn is_tab_row(&self, line: isize) -> bool {
+        let tab_row = if self.header_line.is_empty() { 0 } else { 1 };
+        line == tab_row
+    }
+
+    fn is_bar_hidden(&self) -> bool {
+        self.config.hide_when_single_tab && self.tabs.len() == 1
+    }
+
+    // the bar's background takes the flash, or the sync color while every tab is synced
+    fn filler_highlight(&self) -> Option<PaletteColor> {
+        let palette = self.mode_info.style.colors;
+        if self.flash_ticks > 0 {
+            Some(palette.orange)
+        } else if self.config.sync_all_indicator && self.all_tabs_synced() {
+            Some(self.config.sync_all_color.unwrap_or(palette.red))
+        } else {
+            None
+        }
+    }
+
+    fn print_tab_line(&mut self) {
+        // the same buffer is filled for every line and every render
+        let mut output = std::mem::take(&mut self.output_buffer);
+        if !self.header_line.is_empty() {
+            self.print_line(&self.header_line, &mut output);
+            println!();
+        }
+        self.print_line(&self.tab_line, &mut output);
+        if !self.status_line.is_empty() {
+            println!();
+            self.print_line(&self.status_line, &mut output);
+        }
+        self.output_buffer = output;
+    }
+
+    // every row gets the left_padding and right_padding tab_line gives the tab row
+    fn pad_row(&self, row: Vec<LinePart>, cols: usize) -> Vec<LinePart> {
+        pad_line(row, false, cols, self.mode_info.style.colors, &self.config)
+    }
+
+    fn print_line(&self, line: &[LinePart], output: &mut String) {
+        self.assemble_line(line, output);
+        if self.config.true_color {
+            print!("{}", output);
         } else {
-            BTreeMap::new()
+            print!("{}", eight_bit_colors(output));
+        }
+    }
+
+    // the escapes of every part, and of the background behind them, into output
+    fn assemble_line(&self, line: &[LinePart], output: &mut String) {
+        let palette = self.mode_info.style.colors;
+        let highlight = self.filler_highlight();
+        output.clear();
+        output.reserve(line.iter().map(|part| part.part.len()).sum());
+        for part in line {
+            match highlight {
+                Some(color) if part.kind == PartKind::Filler => {
+                    let filler = " ".repeat(part.len);
+                    output.push_str(&style!(palette.black, color).paint(filler).to_string());
+                }
+                None if part.kind == PartKind::Filler && self.config.transparent_background => {
+                    output.push_str(&" ".repeat(part.len));
+                }
+                _ => output.push_str(&part.part),
+            }
         }
+        // the terminal's own background shows through the rest of the line
+        if highlight.is_none() && self.config.transparent_background {
+            output.push_str("\u{1b}[49m\u{1b}[0K");
+        } else {
+            let background = highlight.unwrap_or(match palette.theme_hue {
+                ThemeHue::Dark => palette.black,
+                ThemeHue::Light => palette.white,
+            });
+            match background {
+                PaletteColor::Rgb((r, g, b)) => {
+                    output.push_str(&format!("\u{1b}[48;2;{};{};{}m\u{1b}[0K", r, g, b));
+                }
+                PaletteColor::EightBit(color) => {
+                    output.push_str(&format!("\u{1b}[48;5;{}m\u{1b}[0K", color));
+                }
+            };
+        }
+    }
+
+    fn all_tabs_synced(&self) -> bool {
+        !self.tabs.is_empty() && self.tabs.iter().all(|tab| tab.is_sync_panes_active)
+    }
+
+    // the layout the bar this client switched from marked for this bar, taken once
+    // by the bar of the focused tab, so the bars of the other tabs and any bar
+    // loaded later for a new tab, a reload or an attach restore nothing
+    fn take_switch_layout(&mut self) -> Option<ClientLayout> {
+        if self.switch_checked || self.current_session.is_empty() || !self.in_focused_tab() {
+            return None;
+        }
+        let pid = self
+            .clients
+            .iter()
+            .find(|client| client.is_current_client)?
+            .client_pid;
+        self.switch_checked = true;
+        self.pid = pid;
+        // the marker is read from the file, the bar that set it runs in another session
+        let mut on_disk = self.read_layout_file(&self.current_session);
+        let layout = on_disk
+            .get_mut(&pid)
+            .filter(|layout| layout.switched_at.is_some())?;
+        let layout = take_switch_marker(layout, unix_now());
+        if let Err(e) = self.write_layout_file(&self.current_session, &on_disk) {
+            log_error!("Could not clear the switch marker: {}", e);
+        }
+        layout
+    }
+
+    // tells the bar of the session this client switches to that the layout it left
+    // there is to be restored, see take_switch_layout
+    fn mark_switch(&self, session_name: &str) {
+        let mut on_disk = self.read_layout_file(session_name);
+        let Some(layout) = on_disk.get_mut(&self.pid) else {
+            return;
+        };
+        layout.switched_at = Some(unix_now());
+        if let Err(e) = self.write_layout_file(session_name, &on_disk) {
+            log_error!("Could not mark the switch to {}: {}", session_name, e);
+        }
+    }
+
+    // returns whether the bar needs to be redrawn
+    fn restore_after_switch(&mut self) -> bool {
+        let Some(layout) = self.take_switch_layout() else {
+            return false;
+        };
+        self.restore_floating_geometry(&layout);
+        self.restore_floating_focus(&layout);
+        self.restore_input_mode(&layout);
+        self.restore_synced_tabs(&layout);
+        self.restore_tab_panes(&layout);
+        self.restore_window_start(&layout)
+    }
+
+    fn read_layout_file(&self, session_name: &str) -> BTreeMap<u32, ClientLayout> {
+        let mut clients = self.read_layout_clients(session_name);
+        let now = unix_now();
+        for layout in clients.values_mut().filter(|layout| layout.saved_at == 0) {
+            layout.saved_at = now;
+        }
+        clients
+    }
+
+    fn read_layout_clients(&self, session_name: &str) -> BTreeMap<u32, ClientLayout> {
+        // with both formats written the json file is the one read
+        if self.config.cache_format == CacheFormat::Kdl {
+            let contents =
+                fs::read_to_string(layout_kdl_file(&self.config.cache_dir, session_name));
+            return match contents.ok().as_deref().and_then(layout_from_kdl) {
+                Some((version, clients)) if version <= LAYOUT_CACHE_VERSION => clients,
+                Some((version, _)) => {
+                    log_error!("Layout cache version {} is newer than this plugin", version);
+                    BTreeMap::new()
+                }
+                None => BTreeMap::new(),
+            };
+        }
+        let Ok(contents) = fs::read(layout_cache_file(&self.config.cache_dir, session_name)) else {
+            return BTreeMap::new();
+        };
+        match serde_json::from_slice::<LayoutCacheFile>(&contents) {
+            Ok(cache) if cache.version <= LAYOUT_CACHE_VERSION => cache.clients,
+            Ok(cache) => {
+                log_error!(
+                    "Layout cache version {} is newer than this plugin",
+                    cache.version
+                );
+                BTreeMap::new()
+            }
+            // the next flush writes it back in the current shape
+            Err(_) => serde_json::from_slice(&contents).unwrap_or_default(),
+        }
+    }
+
+    // writes the file in every format cache_format asks for
+    fn write_layout_file(
+        &self,
+        session_name: &str,
+        clients: &BTreeMap<u32, ClientLayout>,
+    ) -> Result<(), String> {
+        let cache_dir = &self.config.cache_dir;
+        let mut result = fs::create_dir_all(cache_dir).map_err(|e| e.to_string());
+        if self.config.cache_format != CacheFormat::Kdl {
+            result = result.and_then(|_| {
+                let cache = LayoutCacheFile {
+                    version: LAYOUT_CACHE_VERSION,
+                    clients: clients.clone(),
+                };
+                let contents = serde_json::to_vec_pretty(&cache).map_err(|e| e.to_string())?;
+                write_atomically(
+                    &layout_cache_file(cache_dir, session_name),
+                    self.pid,
+                    &contents,
+                )
+                .map_err(|e| e.to_string())
+            });
+        }
+        if self.config.cache_format != CacheFormat::Json {
+            result = result.and_then(|_| {
+                write_atomically(
+                    &layout_kdl_file(cache_dir, session_name),
+                    self.pid,
+                    layout_to_kdl(LAYOUT_CACHE_VERSION, clients).as_bytes(),
+                )
+                .map_err(|e| e.to_string())
+            });
+        }
+        self.layout_reads.borrow_mut().remove(session_name);
+        result
+    }
+
+    // the paged window this client left the session with, tabs may have been
+    // closed in the meantime so the start is kept within them
+    fn restore_window_start(&mut self, layout: &ClientLayout) -> bool {
+        let Some(start) = layout
+            .window_start
+            .filter(|_| self.config.restore_focus_on_switch)
+        else {
+            return false;
+        };
+        self.window_start = Some(start.min(self.tabs.len() - 1));
+        true
+    }
+
+    // the mode this client left the session in, a mode switched to by the bar that
+    // switches sessions would only apply to the session being left
+    fn restore_input_mode(&self, layout: &ClientLayout) {
+        if !self.config.restore_focus_on_switch || !self.config.restore_input_mode {
+            return;
+        }
+        let mode = layout
+            .mode
+            .as_deref()
+            .and_then(|mode| mode.parse::<InputMode>().ok());
+        if let Some(mode) = mode.filter(|mode| *mode != self.mode_info.mode) {
+            switch_to_input_mode(&mode);
+        }
+    }
+
+    // only a client that just attached is switched to the session it was last in,
+    // a client that switched here is where it asked to be
+    fn restore_last_session(&mut self) {
+        if self.startup_checked
+            || !self.config.restore_last_session
+            || self.current_session.is_empty()
+            || self.sessions.is_empty()
+        {
+            return;
+        }
+        let Some(client) = self.clients.iter().find(|client| client.is_current_client) else {
+            return;
+        };
+        self.startup_checked = true;
+        let mut attached = read_attached_clients(&self.config.cache_dir);
+        if !first_attach(&mut attached, client.client_pid, unix_now()) {
+            return;
+        }
+        write_attached_clients(&self.config.cache_dir, &attached);
+        let last_session = read_last_session(&self.config.cache_dir);
+        let session_names: Vec<&str> = self.sessions.iter().map(|s| s.name.as_str()).collect();
+        match startup_session(
+            last_session.as_deref(),
+            &self.current_session,
+            &session_names,
+        ) {
+            Some(name) => switch_session(Some(name)),
+            None => write_last_session(&self.config.cache_dir, &self.current_session),
+        }
+    }
+
+    // the plugin that switches sessions can't reach the panes of the session it
+    // switches to, so the bar of that session puts the floating panes this client
+    // left back
+    fn restore_floating_geometry(&self, layout: &ClientLayout) {
+        if !self.config.restore_floating_geometry {
+            return;
+        }
+        let geometry: BTreeMap<(u32, bool), &FloatingGeometry> = layout
+            .floating
+            .iter()
+            .map(|floating| (floating.pane, floating))
+            .collect();
+        let moves: Vec<(PaneId, FloatingPaneCoordinates)> = self
+            .panes
+            .panes
+            .values()
+            .flatten()
+            .filter(|pane| pane.is_floating)
+            .filter_map(|pane| {
+                let floating = geometry.get(&(pane.id, pane.is_plugin))?;
+                let pane_id = if pane.is_plugin {
+                    PaneId::Plugin(pane.id)
+                } else {
+                    PaneId::Terminal(pane.id)
+                };
+                let coordinates = FloatingPaneCoordinates::new(
+                    Some(floating.x.to_string()),
+                    Some(floating.y.to_string()),
+                    Some(floating.width.to_string()),
+                    Some(floating.height.to_string()),
+                    None,
+                )?;
+                Some((pane_id, coordinates))
+            })
+            .collect();
+        if !moves.is_empty() {
+            change_floating_panes_coordinates(moves);
+        }
+    }
+
+    // zellij focuses a hidden floating pane without showing it, so for a client
+    // that left this session with a floating pane focused the bar shows it again
+    fn restore_floating_focus(&self, layout: &ClientLayout) {
+        if !self.config.restore_focus_on_switch || !layout.pane_floating {
+            return;
+        }
+        let (id, is_plugin) = layout.pane;
+        let focused = get_focused_pane(layout.tab_idx, &self.panes)
+            .is_some_and(|pane| pane.is_floating && pane.id == id && pane.is_plugin == is_plugin);
+        if !focused {
+            if is_plugin {
+                focus_plugin_pane(id, true);
+            } else {
+                focus_terminal_pane(id, true);
+            }
+        }
+    }
+
+    // sync can only be toggled on the focused tab, so every tab that lost its
+    // sync is visited before focus goes back to the tab the client landed on
+    fn restore_synced_tabs(&self, layout: &ClientLayout) {
+        if !self.config.restore_focus_on_switch || !self.config.restore_sync_panes {
+            return;
+        }
+        let tabs_to_sync = tabs_to_resync(&layout.synced_tabs, &self.tabs);
+        if tabs_to_sync.is_empty() {
+            return;
+        }
+        for position in tabs_to_sync {
+            // tabs are indexed starting from 1 so we need to add 1
+            switch_tab_to(position as u32 + 1);
+            toggle_active_tab_sync();
+        }
+        switch_tab_to(self.active_tab_idx() as u32);
+    }
+
+    fn in_focused_tab(&self) -> bool {
+        let Some(tab) = get_focused_tab(&self.tabs) else {
+            return false;
+        };
+        self.panes.panes.get(&tab.position).is_some_and(|panes| {
+            panes
+                .iter()
+                .any(|pane| pane.is_plugin && pane.id == self.plugin_id)
+        })
+    }
+
+    // what a timer event moves forward on its own, returns whether the frame
+    // that was showing needs to be redrawn
+    fn advance_tick(&mut self) -> bool {
+        self.timer_running = false;
+        self.tick += 1;
+        let animating = self.is_animating();
+        self.flash_ticks = self.flash_ticks.saturating_sub(1);
+        animating
+    }
+
+    // navigation events are written to the configured cli pipe as json for external scripts
+    fn emit_event(&self, payload: serde_json::Value) {
+        if let Some(pipe_name) = &self.config.event_pipe {
+            cli_pipe_output(pipe_name, &payload.to_string());
+        }
+    }
+
+    // marks the background tabs whose panes changed since the last manifest,
+    // returns whether one of them wasn't marked yet and so started a new burst
+    fn record_activity(&mut self, panes: &PaneManifest) -> bool {
+        let active_position = self.active_tab_idx().saturating_sub(1);
+        let mut new_activity = false;
+        for position in tabs_with_activity(&self.panes, panes) {
+            if position != active_position {
+                new_activity |= self.tab_activity.insert(position);
+            }
+        }
+        new_activity
+    }
+
+    // like sync, a pane can only be focused by visiting its tab, so every tab whose
+    // focus moved is visited before focus goes back to the tab the client landed on,
+    // switch_session_with_focus already focused the pane of that one
+    fn restore_tab_panes(&self, layout: &ClientLayout) {
+        if !self.config.restore_focus_on_switch || !self.config.restore_tab_panes {
+            return;
+        }
+        let landing = self.active_tab_idx().saturating_sub(1);
+        let moved = panes_to_refocus(&layout.tab_panes, landing, &self.panes);
+        if moved.is_empty() {
+            return;
+        }
+        for (id, is_plugin) in moved {
+            if is_plugin {
+                focus_plugin_pane(id, false);
+            } else {
+                focus_terminal_pane(id, false);
+            }
+        }
+        switch_tab_to(self.active_tab_idx() as u32);
+    }
+
+    // only called when a background tab starts a new burst of activity, so the
+    // cue is given once per burst, returns whether the bar needs to be redrawn
+    fn activity_cue(&mut self) -> bool {
+        match self.config.activity_cue {
+            ActivityCue::Off => false,
+            ActivityCue::Bell => {
+                self.pending_bell = true;
+                true
+            }
+            ActivityCue::Flash => {
+                self.flash_ticks = 1;
+                self.schedule_tick();
+                true
+            }
+        }
+    }
+
+    fn clock(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as i64);
+        let offset = (self.config.clock_utc_offset * 3600.0) as i64;
+        format_clock(&self.config.clock_format, now + offset)
+    }
+
+    fn is_animating(&self) -> bool {
+        self.flash_ticks > 0
+    }
+
+    // all time based segments share a single timer which only keeps
+    // ticking while one of them is animating or waiting on it
+    fn needs_tick(&self) -> bool {
+        self.is_animating()
+            || self.flush_due_tick.is_some()
+            || self.config.shared_indicator
+            || self.config.show_clock
+            || self.armed_close.is_some()
+    }
+
+    fn schedule_tick(&mut self) {
+        if !self.timer_running && self.needs_tick() {
+            set_timeout(self.config.tick_interval);
+            self.timer_running = true;
+        }
+    }
+}
+
+fn current_session_index(sessions: &[SessionInfo], known: &str) -> Option<usize> {
+    sessions
+        .iter()
+        .position(|item| item.is_current_session && item.name == known)
+        .or_else(|| sessions.iter().position(|item| item.is_current_session))
+}
+
+// the event pipe payload for a tab focused from the bar
+fn focus_tab_event(idx: usize, source: &str) -> serde_json::Value {
+    json!({"action": "focus_tab", "tab": idx, "source": source})
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+// lays this client's entry over the entries on disk and drops the ones no client
+// has refreshed within LAYOUT_CACHE_TTL
+fn merge_client_layout(
+    on_disk: &mut BTreeMap<u32, ClientLayout>,
+    pid: u32,
+    layout: Option<ClientLayout>,
+    now: u64,
+) {
+    if let Some(layout) = layout {
+        on_disk.insert(pid, layout);
+    }
+    on_disk.retain(|_, layout| now.saturating_sub(layout.saved_at) < LAYOUT_CACHE_TTL);
+}
+
+// clears the marker mark_switch left on an entry, the entry is only handed back
+// for a switch within SWITCH_MARKER_TTL
+fn take_switch_marker(layout: &mut ClientLayout, now: u64) -> Option<ClientLayout> {
+    let switched_at = layout.switched_at.take()?;
+    (now.saturating_sub(switched_at) < SWITCH_MARKER_TTL).then(|| layout.clone())
+}
+
+impl SwitchSession for State {
+    fn try_switch_session(&mut self) -> () {
+        // a request is only ever answered by the client list that follows it, even
+        // when that list can't serve it, so a later list never acts on a stale one
+        let requested = std::mem::take(&mut self.switch_session_requested);
+        let source_pid = self.switch_session_event_source_pid.take();
+        let target = std::mem::take(&mut self.switch_session_target);
+        if self.current_session.is_empty() {
+            return ();
+        }
+
+        let plugin_pid = self
+            .clients
+            .iter()
+            .find(|client_info| client_info.is_current_client)
+            .map(|v| v.client_pid);
+
+        if plugin_pid.is_none() {
+            return ();
+        }
+
+        self.pid = plugin_pid.unwrap();
+        if requested || source_pid == Some(self.pid) {
+            // the layout is still cached so it is current once switching is unfrozen
+            if self.frozen {
+                log_debug!("Session switching is frozen, dropping the request");
+                self.dump_layout_to_cache();
+                return ();
+            }
+            let going_back = target == SwitchTarget::Back;
+            let target = match target {
+                SwitchTarget::Next => self.next_session.clone(),
+                SwitchTarget::Previous => self.prev_session.clone(),
+                SwitchTarget::Named(name) => Some(name),
+                SwitchTarget::Back => self.pop_session_history(),
+            };
+            if let Some(target) = target {
+                self.dump_layout_to_cache();
+                // going back must not record the way back as a new step
+                if !going_back {
+                    self.push_session_history();
+                }
+
+                self.emit_event(json!({
+                    "action": "switch_session",
+                    "from": self.current_session,
+                    "to": target,
+                }));
+                if self.config.restore_last_session {
+                    write_last_session(&self.config.cache_dir, &target);
+                }
+                // a session that doesn't exist yet has no layout to restore,
+                // switch_session creates it
+                let exists = self.sessions.iter().any(|session| session.name == target);
+                if exists {
+                    self.mark_switch(&target);
+                }
+                let layout = if self.config.restore_focus_on_switch && exists {
+                    self.get_session_layout_info(&target).remove(&self.pid)
+                } else {
+                    None
+                };
+                match layout {
+                    Some(layout) => {
+                        let pane = layout.landing_pane();
+                        let (tab_idx, pane) =
+                            match self.sessions.iter().find(|session| session.name == target) {
+                                Some(session) => landing_focus(session, layout.tab_idx, pane),
+                                None => (layout.tab_idx, Some(pane)),
+                            };
+                        switch_session_with_focus(&target, tab_idx.into(), pane);
+                    }
+                    None => {
+                        switch_session(Some(&target));
+                    }
+                }
+            }
+        }
+    }
+
+    fn dump_layout_to_cache(&mut self) -> () {
+        let Some(layout) = self.current_layout() else {
+            return ();
+        };
+
+        self.last_dump = Some((layout.tab_idx, layout.pane));
+        let mut layout_info = self.get_session_layout_info(&self.current_session);
+        layout_info.insert(self.pid, layout);
+
+        self.layout_cache = Some(layout_info);
+        if self.config.cache_flush_interval <= 0.0 {
+            self.flush_layout_cache();
+        } else if self.flush_due_tick.is_none() {
+            let ticks = (self.config.cache_flush_interval / self.config.tick_interval).ceil();
+            self.flush_due_tick = Some(self.tick + max(ticks as u64, 1));
+            self.schedule_tick();
+        }
+    }
+
+    fn flush_layout_cache(&mut self) {
+        self.flush_due_tick = None;
+        let Some(mut layout_info) = self.layout_cache.take() else {
+            return;
+        };
+
+        // other clients may have written since this map was read, so only this
+        // client's entry is laid over what is on disk now, a WASI plugin has no
+        // file locks to close that window completely
+        let mut on_disk = self.read_layout_file(&self.current_session);
+        merge_client_layout(
+            &mut on_disk,
+            self.pid,
+            layout_info.remove(&self.pid),
+            unix_now(),
+        );
+
+        if let Err(e) = self.write_layout_file(&self.current_session, &on_disk) {
+            log_error!("Could not write layout cache: {}", e);
+        }
+    }
+
+    fn get_session_layout_info(&self, session_name: &str) -> BTreeMap<u32, ClientLayout> {
+        // dumps that are still waiting to be flushed are newer than the file
+        if let Some(layout_info) = self.layout_cache.as_ref() {
+            if session_name == self.current_session {
+                return layout_info.clone();
+            }
+        }
+        // a switch reads the same files more than once, what was read is kept
+        // until the next SessionUpdate or a write of the file
+        if let Some(layout_info) = self.layout_reads.borrow().get(session_name) {
+            return layout_info.clone();
+        }
+        let layout_info = self.read_layout_file(session_name);
+        self.layout_reads
+            .borrow_mut()
+            .insert(session_name.to_string(), layout_info.clone());
+        layout_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floating() -> Vec<FloatingGeometry> {
+        vec![
+            FloatingGeometry {
+                pane: (7, false),
+                x: 10,
+                y: 5,
+                width: 80,
+                height: 20,
+            },
+            FloatingGeometry {
+                pane: (2, true),
+                x: 0,
+                y: 1,
+                width: 30,
+                height: 12,
+            },
+        ]
+    }
+
+    // a layout with nothing optional, one per optional field and one with all of them
+    fn layouts() -> Vec<ClientLayout> {
+        let plain = ClientLayout {
+            tab_idx: 1,
+            pane: (2, false),
+            saved_at: 1_700_000_000,
+            ..Default::default()
+        };
+        vec![
+            plain.clone(),
+            ClientLayout {
+                pane_floating: true,
+                ..plain.clone()
+            },
+            ClientLayout {
+                mode: Some(String::from("Locked")),
+                ..plain.clone()
+            },
+            ClientLayout {
+                synced_tabs: vec![0, 2, 5],
+                ..plain.clone()
+            },
+            ClientLayout {
+                floating: floating(),
+                ..plain.clone()
+            },
+            ClientLayout {
+                window_start: Some(3),
+                ..plain.clone()
+            },
+            ClientLayout {
+                tab_panes: BTreeMap::from([(0, (3, false)), (2, (7, true))]),
+                ..plain.clone()
+            },
+            ClientLayout {
+                switched_at: Some(1_700_000_100),
+                ..plain.clone()
+            },
+            ClientLayout {
+                pane_floating: true,
+                mode: Some(String::from("Locked")),
+                synced_tabs: vec![0, 2, 5],
+                floating: floating(),
+                window_start: Some(3),
+                tab_panes: BTreeMap::from([(0, (3, false)), (2, (7, true))]),
+                switched_at: Some(1_700_000_100),
+                ..plain
+            },
+        ]
+    }
+
+    #[test]
+    fn layouts_round_trip_through_both_formats() {
+        for layout in layouts() {
+            let clients = BTreeMap::from([(4242, layout)]);
+            let cache = LayoutCacheFile {
+                version: LAYOUT_CACHE_VERSION,
+                clients: clients.clone(),
+            };
+            let json = serde_json::to_string(&cache).unwrap();
+            let read: LayoutCacheFile = serde_json::from_str(&json).unwrap();
+            assert_eq!(read.clients, clients);
+            let kdl = layout_to_kdl(LAYOUT_CACHE_VERSION, &clients);
+            assert_eq!(layout_from_kdl(&kdl), Some((LAYOUT_CACHE_VERSION, clients)));
+        }
+    }
+
+    #[test]
+    fn optional_fields_are_neither_written_nor_needed() {
+        let plain = r#"{"tab_idx":1,"pane":[2,false],"pane_floating":false,"saved_at":0}"#;
+        let layout = ClientLayout {
+            tab_idx: 1,
+            pane: (2, false),
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_string(&layout).unwrap(), plain);
+        let read: ClientLayout = serde_json::from_str(r#"{"tab_idx":1,"pane":[2,false]}"#).unwrap();
+        assert_eq!(read, layout);
+    }
+
+    #[test]
+    fn input_modes_are_cached_by_name() {
+        for mode in [InputMode::Normal, InputMode::Locked, InputMode::Scroll] {
+            assert_eq!(format!("{:?}", mode).parse::<InputMode>().ok(), Some(mode));
+        }
+    }
+
+    #[test]
+    fn the_landing_pane_falls_back_to_the_single_pane() {
+        let mut layout = ClientLayout {
+            tab_idx: 1,
+            pane: (2, false),
+            ..Default::default()
+        };
+        assert_eq!(layout.landing_pane(), (2, false));
+        layout.tab_panes = BTreeMap::from([(0, (3, false)), (1, (7, true))]);
+        assert_eq!(layout.landing_pane(), (7, true));
+    }
+
+    #[test]
+    fn a_switch_marker_is_taken_once() {
+        let mut layout = ClientLayout {
+            tab_idx: 1,
+            switched_at: Some(1000),
+            ..Default::default()
+        };
+        let taken = take_switch_marker(&mut layout, 1000 + SWITCH_MARKER_TTL - 1).unwrap();
+        assert_eq!(taken.tab_idx, 1);
+        assert_eq!(taken.switched_at, None);
+        assert_eq!(layout.switched_at, None);
+        assert_eq!(take_switch_marker(&mut layout, 1001), None);
+    }
+
+    #[test]
+    fn a_stale_switch_marker_is_cleared_without_a_restore() {
+        let mut layout = ClientLayout {
+            switched_at: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(
+            take_switch_marker(&mut layout, 1000 + SWITCH_MARKER_TTL),
+            None
+        );
+        assert_eq!(layout.switched_at, None);
+    }
+
+    #[test]
+    fn a_switch_marks_only_the_clients_own_entry() {
+        let mut state = cache_state("mark");
+        state.pid = 12;
+        let clients =
+            BTreeMap::from([(12, ClientLayout::default()), (13, ClientLayout::default())]);
+        state.write_layout_file("work", &clients).unwrap();
+        state.mark_switch("work");
+        let read = state.read_layout_file("work");
+        assert!(read[&12].switched_at.is_some());
+        assert_eq!(read[&13].switched_at, None);
+        // a session this client never left has nothing to mark
+        state.mark_switch("other");
+        assert!(state.read_layout_file("other").is_empty());
+        let _ = fs::remove_dir_all(&state.config.cache_dir);
+    }
+
+    #[test]
+    fn the_timer_is_only_subscribed_to_when_something_ticks() {
+        assert!(!Config::default().needs_timer());
+        let flash = Config {
+            activity_cue: ActivityCue::Flash,
+            ..Default::default()
+        };
+        assert!(flash.needs_timer());
+        let throttled = Config {
+            cache_flush_interval: 2.0,
+            ..Default::default()
+        };
+        assert!(throttled.needs_timer());
+    }
+
+    #[test]
+    fn a_tick_advances_the_flash_until_it_is_over() {
+        let mut state = State {
+            flash_ticks: 2,
+            timer_running: true,
+            ..Default::default()
+        };
+        assert!(state.advance_tick());
+        assert_eq!((state.tick, state.flash_ticks), (1, 1));
+        assert!(!state.timer_running);
+        // still flashing, so the timer is scheduled again
+        assert!(state.needs_tick());
+        assert!(state.advance_tick());
+        assert_eq!(state.flash_ticks, 0);
+        // the last frame is redrawn once more and then the timer stops
+        assert!(!state.needs_tick());
+        assert!(!state.advance_tick());
+    }
+
+    #[test]
+    fn pending_work_keeps_the_timer_going() {
+        let mut state = State::default();
+        assert!(!state.needs_tick());
+        state.flush_due_tick = Some(3);
+        assert!(state.needs_tick());
+        state.flush_due_tick = None;
+        state.armed_close = Some((1, Instant::now()));
+        assert!(state.needs_tick());
+    }
+
+    #[test]
+    fn clicking_a_tab_emits_a_focus_event() {
+        let part = |len: usize, tab_index: Option<usize>, kind: PartKind| LinePart {
+            part: " ".repeat(len),
+            len,
+            tab_index,
+            kind,
+        };
+        let tab_line = vec![
+            part(4, None, PartKind::SessionName),
+            part(5, Some(0), PartKind::Tab),
+            part(5, Some(1), PartKind::Tab),
+        ];
+        let idx = get_tab_to_focus(&tab_line, 1, 11).unwrap();
+        assert_eq!(
+            focus_tab_event(idx, "click").to_string(),
+            r#"{"action":"focus_tab","source":"click","tab":2}"#
+        );
+    }
+
+    #[test]
+    fn the_bar_hides_only_with_a_single_tab() {
+        let mut state = State {
+            config: Config {
+                hide_when_single_tab: true,
+                ..Default::default()
+            },
+            tabs: vec![TabInfo::default()],
+            ..Default::default()
+        };
+        assert!(state.is_bar_hidden());
+        state.tabs.push(TabInfo::default());
+        assert!(!state.is_bar_hidden());
+        // off by default
+        state.tabs.pop();
+        state.config.hide_when_single_tab = false;
+        assert!(!state.is_bar_hidden());
+    }
+
+    #[test]
+    fn the_session_label_carries_its_position() {
+        let mut state = State {
+            config: Config {
+                show_session_name: true,
+                session_count: true,
+                ..Default::default()
+            },
+            session_position: Some((2, 3)),
+            ..Default::default()
+        };
+        state.mode_info.session_name = Some("work".to_string());
+        assert_eq!(state.session_label().as_deref(), Some("work (2/3)"));
+        state.config.session_count = false;
+        assert_eq!(state.session_label().as_deref(), Some("work"));
+    }
+
+    fn tabs_with_focus(titles: &[&str]) -> State {
+        let panes = titles
+            .iter()
+            .enumerate()
+            .map(|(position, title)| {
+                let pane = PaneInfo {
+                    id: position as u32,
+                    title: title.to_string(),
+                    is_focused: true,
+                    ..Default::default()
+                };
+                (position, vec![pane])
+            })
+            .collect();
+        State {
+            tabs: (0..titles.len())
+                .map(|position| TabInfo {
+                    position,
+                    active: position == 1,
+                    ..Default::default()
+                })
+                .collect(),
+            panes: PaneManifest { panes },
+            // tabs are indexed starting from 1
+            active_tab_idx: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn the_pane_title_is_the_focused_pane_of_the_active_tab() {
+        let state = tabs_with_focus(&["htop", "vim main.rs"]);
+        assert_eq!(state.focused_pane_title().as_deref(), Some("vim main.rs"));
+    }
+
+    #[test]
+    fn no_pane_title_without_a_titled_focused_pane() {
+        assert_eq!(tabs_with_focus(&["htop", "  "]).focused_pane_title(), None);
+        let state = State {
+            tabs: tabs_with_focus(&["htop", "vim"]).tabs,
+            ..Default::default()
+        };
+        assert_eq!(state.focused_pane_title(), None);
+    }
+
+    fn titled(titles: &[&str]) -> PaneManifest {
+        let panes = titles
+            .iter()
+            .enumerate()
+            .map(|(position, title)| {
+                let pane = PaneInfo {
+                    title: title.to_string(),
+                    ..Default::default()
+                };
+                (position, vec![pane])
+            })
+            .collect();
+        PaneManifest { panes }
+    }
+
+    #[test]
+    fn background_activity_is_cued_once_per_burst() {
+        let mut state = State {
+            active_tab_idx: 1,
+            tabs: vec![TabInfo::default(), TabInfo::default()],
+            panes: titled(&["vim", "cargo build"]),
+            ..Default::default()
+        };
+        let mut burst = vec![];
+        for title in ["Compiling", "Finished", "Running"] {
+            let panes = titled(&["vim", title]);
+            burst.push(state.record_activity(&panes));
+            state.panes = panes;
+        }
+        assert_eq!(burst, vec![true, false, false]);
+        // focusing the tab clears the mark, so the next burst is cued again
+        state.tab_activity.clear();
+        assert!(state.record_activity(&titled(&["vim", "done"])));
+    }
+
+    #[test]
+    fn activity_in_the_active_tab_is_not_cued() {
+        let mut state = State {
+            active_tab_idx: 1,
+            tabs: vec![TabInfo::default(), TabInfo::default()],
+            panes: titled(&["vim", "htop"]),
+            ..Default::default()
+        };
+        assert!(!state.record_activity(&titled(&["vim main.rs", "htop"])));
+        assert!(state.tab_activity.is_empty());
+    }
+
+    #[test]
+    fn more_sessions_counts_the_other_sessions() {
+        let session = |name: &str| SessionInfo {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let mut state = State {
+            current_session: "work".to_string(),
+            sessions: vec![session("play"), session("work"), session("notes")],
+            ..Default::default()
+        };
+        assert_eq!(state.other_session_count(), 2);
+        state.sessions = vec![session("work")];
+        assert_eq!(state.other_session_count(), 0);
+    }
+
+    #[test]
+    fn a_tab_update_keeps_a_window_moved_by_hand() {
+        let mut state = State {
+            config: Config {
+                sticky_window: true,
+                ..Default::default()
+            },
+            active_tab_idx: 2,
+            tabs: vec![TabInfo::default(); 8],
+            window_start: Some(3),
+            ..Default::default()
+        };
+        state.scroll_window(true);
+        assert_eq!(state.window_start, Some(4));
+        // a rename or a new pane elsewhere leaves the active tab where it was
+        state.follow_active_tab(2);
+        assert_eq!(state.window_start, Some(4));
+        state.follow_active_tab(5);
+        assert_eq!(state.window_start, None);
+    }
+
+    #[test]
+    fn without_sticky_window_every_tab_update_recenters() {
+        let mut state = State {
+            active_tab_idx: 2,
+            window_start: Some(3),
+            ..Default::default()
+        };
+        state.follow_active_tab(2);
+        assert_eq!(state.window_start, None);
+    }
+
+    fn synced(tabs: &[bool]) -> State {
+        State {
+            config: Config {
+                sync_all_indicator: true,
+                sync_all_color: Some(PaletteColor::EightBit(9)),
+                ..Default::default()
+            },
+            tabs: tabs
+                .iter()
+                .map(|synced| TabInfo {
+                    is_sync_panes_active: *synced,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn every_synced_tab_turns_the_bar_the_sync_color() {
+        let state = synced(&[true, true, true]);
+        assert!(state.all_tabs_synced());
+        assert_eq!(state.filler_highlight(), Some(PaletteColor::EightBit(9)));
+        // the tabs leave their own sync mark to the bar
+        assert!(!shows_sync(&state.tabs[0], true, &state.config));
+    }
+
+    #[test]
+    fn partly_synced_tabs_keep_their_own_marks() {
+        let state = synced(&[true, false, true]);
+        assert!(!state.all_tabs_synced());
+        assert_eq!(state.filler_highlight(), None);
+        assert!(shows_sync(&state.tabs[0], false, &state.config));
+        assert!(!synced(&[]).all_tabs_synced());
+    }
+
+    #[test]
+    fn only_the_tab_row_takes_clicks() {
+        let mut state = State::default();
+        assert!(state.is_tab_row(0));
+        assert!(!state.is_tab_row(1));
+        // with the header on top the tabs move down a row, the header and the
+        // status row below are left alone
+        state.header_line = vec![LinePart::default()];
+        assert!(!state.is_tab_row(0));
+        assert!(state.is_tab_row(1));
+        assert!(!state.is_tab_row(2));
+    }
+
+    #[test]
+    fn an_aliased_tab_renders_the_alias_and_focuses_by_position() {
+        let mut state = State::default();
+        state
+            .tab_aliases
+            .insert("editor".to_string(), "code".to_string());
+        state
+            .config
+            .tab_aliases
+            .insert("editor".to_string(), "ignored".to_string());
+        state
+            .config
+            .tab_aliases
+            .insert("logs".to_string(), "tail".to_string());
+        // the session's own alias wins over the configured one
+        assert_eq!(state.tab_alias("editor"), Some("code"));
+        assert_eq!(state.tab_alias("logs"), Some("tail"));
+        assert_eq!(state.tab_alias("shell"), None);
+        let tab_line: Vec<LinePart> = ["shell", "editor"]
+            .iter()
+            .enumerate()
+            .map(|(position, name)| {
+                let tab = TabInfo {
+                    position,
+                    name: name.to_string(),
+                    active: position == 0,
+                    ..Default::default()
+                };
+                let name = state.tab_alias(name).unwrap_or(name);
+                tab_style(
+                    tab_label(position + 1, 2, name, &state.config),
+                    &tab,
+                    Palette::default(),
+                    &state.config,
+                    None,
+                    InputMode::Normal,
+                    TabMarks::default(),
+                )
+            })
+            .collect();
+        assert!(tab_line[1].part.contains("2 code"));
+        let col = tab_line[0].len;
+        assert_eq!(get_tab_to_focus(&tab_line, 1, col), Some(2));
+    }
+
+    fn focused_on(state: &mut State, id: u32) {
+        let pane = PaneInfo {
+            id,
+            is_focused: true,
+            ..Default::default()
+        };
+        state.panes = PaneManifest {
+            panes: [(0, vec![pane])].into_iter().collect(),
+        };
+    }
+
+    #[test]
+    fn dumps_within_the_flush_interval_are_written_once() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("compact-bar-flush-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&cache_dir);
+        let mut state = State {
+            config: Config {
+                cache_flush_interval: 2.0,
+                cache_dir: cache_dir.clone(),
+                ..Default::default()
+            },
+            current_session: "work".to_string(),
+            pid: 7,
+            tabs: vec![TabInfo {
+                active: true,
+                ..Default::default()
+            }],
+            // the timer is already running, so nothing is scheduled with the host
+            timer_running: true,
+            ..Default::default()
+        };
+        let file = layout_cache_file(&cache_dir, "work");
+        for id in [1, 2, 3] {
+            focused_on(&mut state, id);
+            state.dump_layout_to_cache();
+        }
+        assert!(!file.exists());
+        // the flush stays due from the first dump
+        assert_eq!(state.flush_due_tick, Some(2));
+        state.flush_layout_cache();
+        assert!(file.exists());
+        let written = state.read_layout_file("work");
+        assert_eq!(written[&7].pane, (3, false));
+        assert_eq!(state.flush_due_tick, None);
+        let _ = fs::remove_dir_all(&cache_dir);
+    }
+
+    #[test]
+    fn the_dirty_marker_shows_after_a_focus_change_until_the_next_dump() {
+        let mut state = State {
+            config: Config {
+                dirty_marker: true,
+                // held back, so the dumps below don't reach the disk
+                cache_flush_interval: 60.0,
+                ..Default::default()
+            },
+            current_session: "work".to_string(),
+            tabs: vec![TabInfo {
+                active: true,
+                ..Default::default()
+            }],
+            timer_running: true,
+            ..Default::default()
+        };
+        focused_on(&mut state, 1);
+        assert!(state.layout_dirty());
+        state.dump_layout_to_cache();
+        assert!(!state.layout_dirty());
+        focused_on(&mut state, 2);
+        assert!(state.layout_dirty());
+        state.dump_layout_to_cache();
+        assert!(!state.layout_dirty());
+    }
+
+    #[test]
+    fn page_window_scrolling_moves_the_window_and_not_the_focus() {
+        let mut state = State {
+            config: Config {
+                scroll_action: ScrollAction::PageWindow,
+                ..Default::default()
+            },
+            active_tab_idx: 2,
+            tabs: vec![TabInfo::default(); 10],
+            tab_line: (0..4)
+                .map(|idx| LinePart {
+                    len: 6,
+                    tab_index: Some(idx),
+                    kind: PartKind::Tab,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        assert!(state.scroll(true));
+        assert!(state.scroll(true));
+        assert_eq!(state.window_start, Some(2));
+        assert!(state.scroll(false));
+        assert_eq!(state.window_start, Some(1));
+        // the window stops at the first tab
+        state.scroll(false);
+        state.scroll(false);
+        assert_eq!(state.window_start, Some(0));
+        assert_eq!(state.active_tab_idx, 2);
+    }
+
+    #[test]
+    fn ping_reports_nulls_before_the_first_updates() {
+        assert_eq!(
+            State::default().status(),
+            json!({
+                "session": null,
+                "tab_count": 0,
+                "active_tab": null,
+                "switch_pending": false,
+                "client_pid": null,
+                "frozen": false,
+            })
+        );
+    }
+
+    #[test]
+    fn ping_reports_what_the_bar_has_seen() {
+        let state = State {
+            current_session: "work".to_string(),
+            tabs: vec![TabInfo::default(); 3],
+            active_tab_idx: 2,
+            pid: 4242,
+            switch_session_requested: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            state.status(),
+            json!({
+                "session": "work",
+                "tab_count": 3,
+                "active_tab": 2,
+                "switch_pending": true,
+                "client_pid": 4242,
+                "frozen": false,
+            })
+        );
+    }
+
+    fn saved_at(saved_at: u64) -> ClientLayout {
+        ClientLayout {
+            saved_at,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merge_lays_the_client_over_the_file() {
+        let now = 10 * LAYOUT_CACHE_TTL;
+        let mut on_disk = BTreeMap::from([(1, saved_at(now - 5)), (2, saved_at(now - 5))]);
+        let layout = ClientLayout {
+            tab_idx: 3,
+            saved_at: now,
+            ..Default::default()
+        };
+        merge_client_layout(&mut on_disk, 1, Some(layout), now);
+        assert_eq!(on_disk.len(), 2);
+        assert_eq!(on_disk[&1].tab_idx, 3);
+        // written by another client meanwhile
+        assert_eq!(on_disk[&2].saved_at, now - 5);
+    }
+
+    #[test]
+    fn merge_prunes_only_entries_past_their_age() {
+        let now = 10 * LAYOUT_CACHE_TTL;
+        let mut on_disk = BTreeMap::from([
+            // a client that moved to another session an hour ago
+            (2, saved_at(now - 60 * 60)),
+            // a client gone for longer than the ttl
+            (99, saved_at(now - LAYOUT_CACHE_TTL)),
+        ]);
+        merge_client_layout(&mut on_disk, 1, Some(saved_at(now)), now);
+        assert_eq!(on_disk.keys().copied().collect::<Vec<u32>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn merge_without_a_layout_still_prunes() {
+        let now = 10 * LAYOUT_CACHE_TTL;
+        let mut on_disk = BTreeMap::from([(99, saved_at(now - 2 * LAYOUT_CACHE_TTL))]);
+        merge_client_layout(&mut on_disk, 1, None, now);
+        assert!(on_disk.is_empty());
+    }
+
+    fn cache_state(name: &str) -> State {
+        let cache_dir =
+            std::env::temp_dir().join(format!("compact-bar-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&cache_dir);
+        fs::create_dir_all(&cache_dir).unwrap();
+        State {
+            config: Config {
+                cache_dir,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_bare_map_from_before_versioning_is_read_as_version_0() {
+        let state = cache_state("v0");
+        let file = layout_cache_file(&state.config.cache_dir, "work");
+        fs::write(&file, r#"{"12":{"tab_idx":2,"pane":[3,false]}}"#).unwrap();
+        let clients = state.read_layout_file("work");
+        assert_eq!(clients[&12].tab_idx, 2);
+        assert_eq!(clients[&12].pane, (3, false));
+        // it counts as saved when it was read, so it isn't pruned straight away
+        assert!(clients[&12].saved_at > 0);
+        let _ = fs::remove_dir_all(&state.config.cache_dir);
+    }
+
+    #[test]
+    fn a_cache_from_a_newer_plugin_is_not_read() {
+        let state = cache_state("newer");
+        let file = layout_cache_file(&state.config.cache_dir, "work");
+        let newer = json!({
+            "version": LAYOUT_CACHE_VERSION + 1,
+            "clients": {"12": {"tab_idx": 2, "pane": [3, false]}},
+        });
+        fs::write(&file, newer.to_string()).unwrap();
+        assert!(state.read_layout_file("work").is_empty());
+        let _ = fs::remove_dir_all(&state.config.cache_dir);
+    }
+
+    #[test]
+    fn a_client_list_without_the_current_client_drops_the_request() {
+        let mut state = State {
+            current_session: "work".to_string(),
+            next_session: Some("play".to_string()),
+            switch_session_requested: true,
+            switch_session_event_source_pid: Some(4242),
+            switch_session_target: SwitchTarget::Named("notes".to_string()),
+            ..Default::default()
+        };
+        state.try_switch_session();
+        assert!(!state.switch_session_requested);
+        assert_eq!(state.switch_session_event_source_pid, None);
+        assert_eq!(state.switch_session_target, SwitchTarget::Next);
+        // a later list has nothing left to act on, so it can't switch
+        state.try_switch_session();
+        assert_eq!(state.pid, 0);
+        assert!(!state.switch_session_requested);
+    }
+
+    #[test]
+    fn the_reused_buffer_assembles_the_same_bytes() {
+        let state = State {
+            tabs: vec![TabInfo::default(); 2],
+            ..Default::default()
+        };
+        let parts: Vec<LinePart> = ["\u{1b}[1m 1 work \u{1b}[0m", " 2 play ", "   "]
+            .iter()
+            .enumerate()
+            .map(|(i, part)| LinePart {
+                part: part.to_string(),
+                len: 8,
+                tab_index: Some(i),
+                kind: if i == 2 {
+                    PartKind::Filler
+                } else {
+                    PartKind::Tab
+                },
+            })
+            .collect();
+        // what the line was built with before the buffer was kept between renders
+        let expected = parts
+            .iter()
+            .fold(String::new(), |output, part| output + &part.part)
+            + "\u{1b}[48;5;0m\u{1b}[0K";
+        let mut output = String::from("left over from the last render");
+        state.assemble_line(&parts, &mut output);
+        assert_eq!(output, expected);
+        // the second render into the same buffer is the same again
+        state.assemble_line(&parts, &mut output);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn a_session_listed_twice_resolves_to_the_one_the_bar_knows() {
+        let session = |name: &str, current: bool| SessionInfo {
+            name: name.to_string(),
+            is_current_session: current,
+            ..Default::default()
+        };
+        let sessions = vec![
+            session("alpha", false),
+            session("work", true),
+            session("work", true),
+            session("work (1)", true),
+        ];
+        assert_eq!(current_session_index(&sessions, "work (1)"), Some(3));
+        assert_eq!(current_session_index(&sessions, "work"), Some(1));
+        // before the bar knows its session the first current entry is taken
+        assert_eq!(current_session_index(&sessions, ""), Some(1));
+        assert_eq!(current_session_index(&sessions[..1], "alpha"), None);
+    }
+
+    #[test]
+    fn a_shrinking_tab_list_clamps_the_active_tab() {
+        let mut state = State {
+            active_tab_idx: 5,
+            tabs: vec![TabInfo::default(); 5],
+            ..Default::default()
+        };
+        // the tabs shrank before the TabUpdate naming the new active tab arrived
+        state.tabs.truncate(3);
+        assert_eq!(state.active_tab_idx(), 3);
+        let tab_count = state.tabs.len();
+        assert_eq!(
+            next_tab_index(state.active_tab_idx(), tab_count, true),
+            (1, true)
+        );
+        assert_eq!(
+            next_tab_index(state.active_tab_idx(), tab_count, false),
+            (2, false)
+        );
+        assert_eq!(
+            scroll_target(state.active_tab_idx(), tab_count, 1, true, false),
+            3
+        );
+        state.tabs.clear();
+        assert_eq!(state.active_tab_idx(), 0);
     }
 }
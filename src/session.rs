@@ -0,0 +1,388 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::log::log_error;
+
+const LAST_SESSION_FILE: &str = "compact-bar-last-session";
+const SESSION_HISTORY_FILE: &str = "compact-bar-session-history.json";
+const ATTACHED_CLIENTS_FILE: &str = "compact-bar-attached-clients.json";
+// zellij doesn't tell plugins when a client detaches, so a client is forgotten
+// this long after it attached, a client still attached by then has already
+// written where it is and is not moved
+const ATTACHED_CLIENT_TTL: u64 = 24 * 60 * 60;
+
+// sessions listed in `order` come first, in that order, followed by the
+// remaining sessions alphabetically, listed sessions that don't exist are skipped
+pub(crate) fn session_cycle<'a>(session_names: &[&'a str], order: &[String]) -> Vec<&'a str> {
+    let mut cycle: Vec<&str> = vec![];
+    for name in order {
+        if let Some(session) = session_names.iter().find(|s| **s == name.as_str()) {
+            if !cycle.contains(session) {
+                cycle.push(*session);
+            }
+        }
+    }
+    let mut unlisted: Vec<&str> = session_names
+        .iter()
+        .filter(|name| !cycle.contains(name))
+        .copied()
+        .collect();
+    unlisted.sort();
+    // zellij can briefly list a session twice while it is created, the copy would
+    // otherwise be the neighbour of the session it copies
+    unlisted.dedup();
+    cycle.append(&mut unlisted);
+    cycle
+}
+
+pub(crate) fn next_session(
+    session_names: &[&str],
+    current_session: &str,
+    order: &[String],
+) -> Option<String> {
+    neighbour_session(session_names, current_session, order, true)
+}
+
+pub(crate) fn previous_session(
+    session_names: &[&str],
+    current_session: &str,
+    order: &[String],
+) -> Option<String> {
+    neighbour_session(session_names, current_session, order, false)
+}
+
+fn neighbour_session(
+    session_names: &[&str],
+    current_session: &str,
+    order: &[String],
+    forward: bool,
+) -> Option<String> {
+    let cycle = session_cycle(session_names, order);
+    if cycle.len() < 2 {
+        return None;
+    }
+    let current_index = cycle.iter().position(|name| *name == current_session)?;
+    let index = if forward {
+        (current_index + 1) % cycle.len()
+    } else {
+        (current_index + cycle.len() - 1) % cycle.len()
+    };
+    Some(cycle[index].to_string())
+}
+
+// 1 based position of the current session in the cycle, along with the number of sessions
+pub(crate) fn session_position(
+    session_names: &[&str],
+    current_session: &str,
+    order: &[String],
+) -> Option<(usize, usize)> {
+    let cycle = session_cycle(session_names, order);
+    let current_index = cycle.iter().position(|name| *name == current_session)?;
+    Some((current_index + 1, cycle.len()))
+}
+
+pub(crate) fn read_last_session(cache_dir: &Path) -> Option<String> {
+    let name = fs::read_to_string(cache_dir.join(LAST_SESSION_FILE)).ok()?;
+    Some(name.trim().to_string()).filter(|name| !name.is_empty())
+}
+
+pub(crate) fn write_last_session(cache_dir: &Path, session_name: &str) {
+    let result = fs::create_dir_all(cache_dir)
+        .and_then(|_| fs::write(cache_dir.join(LAST_SESSION_FILE), session_name));
+    if let Err(e) = result {
+        log_error!("Could not write last session: {}", e);
+    }
+}
+
+// clients seen by the bar of any session, with when they were first seen
+pub(crate) fn read_attached_clients(cache_dir: &Path) -> BTreeMap<u32, u64> {
+    fs::read_to_string(cache_dir.join(ATTACHED_CLIENTS_FILE))
+        .ok()
+        .and_then(|clients| serde_json::from_str(&clients).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn write_attached_clients(cache_dir: &Path, clients: &BTreeMap<u32, u64>) {
+    let result = serde_json::to_vec(clients)
+        .map_err(|e| e.to_string())
+        .and_then(|clients| {
+            fs::create_dir_all(cache_dir)
+                .and_then(|_| fs::write(cache_dir.join(ATTACHED_CLIENTS_FILE), clients))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        log_error!("Could not write attached clients: {}", e);
+    }
+}
+
+// a session switch keeps the client process, so only a pid none of the bars has
+// seen yet is a client that just attached, it is recorded so the bar of the
+// session it switches to doesn't take it for a new one
+pub(crate) fn first_attach(clients: &mut BTreeMap<u32, u64>, pid: u32, now: u64) -> bool {
+    clients.retain(|_, seen| now.saturating_sub(*seen) < ATTACHED_CLIENT_TTL);
+    if clients.contains_key(&pid) {
+        return false;
+    }
+    clients.insert(pid, now);
+    true
+}
+
+// sessions left through the bar, the most recent last
+pub(crate) fn read_session_history(cache_dir: &Path) -> Vec<String> {
+    fs::read_to_string(cache_dir.join(SESSION_HISTORY_FILE))
+        .ok()
+        .and_then(|history| serde_json::from_str(&history).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn write_session_history(cache_dir: &Path, history: &[String]) {
+    let result = serde_json::to_vec(history)
+        .map_err(|e| e.to_string())
+        .and_then(|history| {
+            fs::create_dir_all(cache_dir)
+                .and_then(|_| fs::write(cache_dir.join(SESSION_HISTORY_FILE), history))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        log_error!("Could not write session history: {}", e);
+    }
+}
+
+// session names may hold `/` or other characters a path can't, so everything
+// but a small safe set is percent encoded into a single flat file name, `-` is
+// encoded too since it separates the name from what kind of file it is
+fn safe_file_name(session_name: &str) -> String {
+    let mut name = String::new();
+    for byte in session_name.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'.') {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    // `.` and `..` would name a directory
+    if name.chars().all(|c| c == '.') {
+        name = name.replace('.', "%2E");
+    }
+    name
+}
+
+pub(crate) fn layout_cache_file(cache_dir: &Path, session_name: &str) -> PathBuf {
+    cache_dir.join(format!("{0}.json", safe_file_name(session_name)))
+}
+
+pub(crate) fn layout_kdl_file(cache_dir: &Path, session_name: &str) -> PathBuf {
+    cache_dir.join(format!("{0}.kdl", safe_file_name(session_name)))
+}
+
+// writes next to `path` first and renames over it, so a reader, or another client
+// writing at the same time, never sees a half written file, `writer` tells the
+// temporary files of different clients apart
+pub(crate) fn write_atomically(path: &Path, writer: u32, contents: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", writer));
+    let temporary = PathBuf::from(temporary);
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temporary);
+    })
+}
+
+fn tab_aliases_file(cache_dir: &Path, session_name: &str) -> PathBuf {
+    cache_dir.join(format!("{0}-aliases.json", safe_file_name(session_name)))
+}
+
+pub(crate) fn read_tab_aliases(cache_dir: &Path, session_name: &str) -> BTreeMap<String, String> {
+    fs::read_to_string(tab_aliases_file(cache_dir, session_name))
+        .ok()
+        .and_then(|aliases| serde_json::from_str(&aliases).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn write_tab_aliases(
+    cache_dir: &Path,
+    session_name: &str,
+    aliases: &BTreeMap<String, String>,
+) {
+    let result = serde_json::to_string_pretty(aliases)
+        .map_err(|e| e.to_string())
+        .and_then(|aliases| {
+            fs::create_dir_all(cache_dir)
+                .and_then(|_| fs::write(tab_aliases_file(cache_dir, session_name), aliases))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        log_error!("Could not write tab aliases: {}", e);
+    }
+}
+
+// the stored session is only worth switching to if it is still around
+pub(crate) fn startup_session<'a>(
+    last_session: Option<&'a str>,
+    current_session: &str,
+    session_names: &[&str],
+) -> Option<&'a str> {
+    last_session.filter(|name| *name != current_session && session_names.contains(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("compact-bar-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn last_session_round_trips() {
+        let dir = cache_dir("last-session");
+        assert_eq!(read_last_session(&dir), None);
+        write_last_session(&dir, "work");
+        assert_eq!(read_last_session(&dir).as_deref(), Some("work"));
+        write_last_session(&dir, "play");
+        assert_eq!(read_last_session(&dir).as_deref(), Some("play"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn startup_switches_only_to_another_live_session() {
+        let sessions = ["play", "work"];
+        assert_eq!(
+            startup_session(Some("work"), "play", &sessions),
+            Some("work")
+        );
+        assert_eq!(startup_session(Some("play"), "play", &sessions), None);
+        assert_eq!(startup_session(Some("gone"), "play", &sessions), None);
+        assert_eq!(startup_session(None, "play", &sessions), None);
+    }
+
+    #[test]
+    fn a_client_is_only_new_once() {
+        let mut clients = BTreeMap::new();
+        assert!(first_attach(&mut clients, 42, 1000));
+        // the bar of the session the client switched to
+        assert!(!first_attach(&mut clients, 42, 1010));
+        assert!(first_attach(&mut clients, 43, 1010));
+    }
+
+    #[test]
+    fn forgotten_clients_count_as_new() {
+        let mut clients = BTreeMap::from([(42, 1000)]);
+        assert!(first_attach(&mut clients, 42, 1000 + ATTACHED_CLIENT_TTL));
+        assert_eq!(clients.get(&42), Some(&(1000 + ATTACHED_CLIENT_TTL)));
+    }
+
+    #[test]
+    fn attached_clients_round_trip() {
+        let dir = cache_dir("attached");
+        assert!(read_attached_clients(&dir).is_empty());
+        let clients = BTreeMap::from([(42, 1000), (43, 2000)]);
+        write_attached_clients(&dir, &clients);
+        assert_eq!(read_attached_clients(&dir), clients);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn hostile_session_names_give_a_single_flat_file() {
+        let dir = cache_dir("flat");
+        let path = layout_cache_file(&dir, "foo/bar baz");
+        assert_eq!(path.parent(), Some(dir.as_path()));
+        assert_eq!(path.file_name().unwrap(), "foo%2Fbar%20baz.json");
+        fs::create_dir_all(&dir).unwrap();
+        write_atomically(&path, 1, b"{}").unwrap();
+        let files: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files, vec![path]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn dot_names_are_not_directories() {
+        assert_eq!(safe_file_name("."), "%2E");
+        assert_eq!(safe_file_name(".."), "%2E%2E");
+        assert_eq!(safe_file_name("a.b"), "a.b");
+    }
+
+    #[test]
+    fn alias_files_never_collide_with_layout_files() {
+        let dir = PathBuf::from("/cache");
+        assert_ne!(
+            layout_cache_file(&dir, "foo-aliases"),
+            tab_aliases_file(&dir, "foo")
+        );
+        assert_eq!(
+            layout_cache_file(&dir, "foo-aliases").file_name().unwrap(),
+            "foo%2Daliases.json"
+        );
+    }
+
+    fn order(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn sessions_follow_the_custom_order_then_the_rest() {
+        let sessions = ["alpha", "zeta", "main", "beta"];
+        // "gone" is listed but not running, so it is skipped
+        let order = order(&["main", "gone", "zeta"]);
+        assert_eq!(
+            session_cycle(&sessions, &order),
+            vec!["main", "zeta", "alpha", "beta"]
+        );
+        assert_eq!(
+            next_session(&sessions, "main", &order).as_deref(),
+            Some("zeta")
+        );
+        assert_eq!(
+            next_session(&sessions, "zeta", &order).as_deref(),
+            Some("alpha")
+        );
+        assert_eq!(
+            next_session(&sessions, "beta", &order).as_deref(),
+            Some("main")
+        );
+        assert_eq!(
+            previous_session(&sessions, "main", &order).as_deref(),
+            Some("beta")
+        );
+    }
+
+    #[test]
+    fn no_next_session_without_another_one() {
+        let order = order(&["main"]);
+        assert_eq!(next_session(&["main"], "main", &order), None);
+        // the current session missing from the list gives nowhere to go
+        assert_eq!(next_session(&["a", "b"], "main", &order), None);
+    }
+
+    #[test]
+    fn session_position_counts_from_one_in_cycle_order() {
+        let sessions = ["beta", "alpha", "main"];
+        let order = order(&["main"]);
+        assert_eq!(session_position(&sessions, "main", &order), Some((1, 3)));
+        assert_eq!(session_position(&sessions, "beta", &order), Some((3, 3)));
+        assert_eq!(session_position(&sessions, "gone", &order), None);
+    }
+
+    #[test]
+    fn a_duplicate_session_is_not_its_own_neighbour() {
+        let sessions = ["alpha", "work", "work", "zeta"];
+        assert_eq!(
+            next_session(&sessions, "work", &[]).as_deref(),
+            Some("zeta")
+        );
+        assert_eq!(
+            previous_session(&sessions, "work", &[]).as_deref(),
+            Some("alpha")
+        );
+        assert_eq!(session_position(&sessions, "zeta", &[]), Some((3, 3)));
+        // two copies of one session leave nowhere to go
+        assert_eq!(next_session(&["work", "work"], "work", &[]), None);
+    }
+}
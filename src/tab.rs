@@ -1,33 +1,827 @@
-use crate::LinePart;
-use ansi_term::ANSIStrings;
-use unicode_width::UnicodeWidthStr;
+use crate::config::Config;
+use crate::{LinePart, PartKind};
+use ansi_term::{ANSIStrings, Style};
+use std::cmp::{max, min, Ordering};
+use std::collections::BTreeMap;
+use unicode_width::UnicodeWidthChar;
 use zellij_tile::prelude::*;
 use zellij_tile_utils::style;
 
-pub fn render_tab(text: String, tab: &TabInfo, palette: Palette) -> LinePart {
-    let tab_text_len = text.width() + 2; // + 2 for padding
+const RIGHT_TO_LEFT_ISOLATE: char = '\u{2067}';
+const POP_DIRECTIONAL_ISOLATE: char = '\u{2069}';
 
-    let tab_styled_text = if tab.active {
-        style!(palette.black, palette.yellow).paint(format!(" {} ", text))
+fn is_rtl_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF
+    )
+}
+
+// the first strongly directional character decides the direction of the whole name
+fn is_rtl(text: &str) -> bool {
+    text.chars()
+        .find(|c| c.is_alphabetic())
+        .is_some_and(is_rtl_char)
+}
+
+// bidi isolates are invisible, so they are never counted towards the width. the
+// width of every part is measured here, so clicks map to the cells the terminal
+// actually drew: wide characters take two cells and combining marks none
+pub fn text_width(text: &str) -> usize {
+    text.chars().fold(0, |width, c| {
+        if c == RIGHT_TO_LEFT_ISOLATE || c == POP_DIRECTIONAL_ISOLATE {
+            width
+        } else {
+            width + c.width().unwrap_or(0)
+        }
+    })
+}
+
+// keeps as many leading characters as fit in width columns
+pub(crate) fn truncate_to_width(text: &str, width: usize) -> String {
+    let mut truncated = String::new();
+    let mut truncated_width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if truncated_width + char_width > width {
+            break;
+        }
+        truncated_width += char_width;
+        truncated.push(c);
+    }
+    truncated
+}
+
+// cuts text longer than width columns so it ends in the ellipsis, keeping a bidi
+// isolate closed if the cut went through it
+pub(crate) fn ellipsize(text: &str, width: usize, ellipsis: &str) -> String {
+    if width == 0 || text_width(text) <= width {
+        return text.to_string();
+    }
+    let mut truncated = truncate_to_width(text, width.saturating_sub(text_width(ellipsis)));
+    if truncated.contains(RIGHT_TO_LEFT_ISOLATE) && !truncated.contains(POP_DIRECTIONAL_ISOLATE) {
+        truncated.push(POP_DIRECTIONAL_ISOLATE);
+    }
+    truncated.push_str(ellipsis);
+    truncated
+}
+
+// centers the text in exactly width columns, cutting it when it doesn't fit
+fn fit_to_width(text: &str, width: usize) -> String {
+    let text = truncate_to_width(text, width);
+    let padding = width.saturating_sub(text_width(&text));
+    let left = padding / 2;
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(padding - left))
+}
+
+fn shows_number(tab_count: usize, config: &Config) -> bool {
+    config.show_tab_index && !(config.hide_single_tab_number && tab_count == 1)
+}
+
+pub fn tab_label(position: usize, tab_count: usize, name: &str, config: &Config) -> String {
+    if !shows_number(tab_count, config) {
+        return name.to_string();
+    }
+    let number = config.number_style.format(position);
+    if config.rtl_aware && is_rtl(name) {
+        // isolate the name so the terminal doesn't reorder the number into it
+        format!(
+            "{}{}{} {}",
+            RIGHT_TO_LEFT_ISOLATE, name, POP_DIRECTIONAL_ISOLATE, number
+        )
+    } else {
+        format!("{} {}", number, name)
+    }
+}
+
+// the label built from tab_format and the columns of its first `{index}`,
+// placeholders that aren't known are left as they are
+pub(crate) fn format_tab(
+    template: &str,
+    position: usize,
+    name: &str,
+    pane_count: usize,
+    tab: &TabInfo,
+    synced: bool,
+    config: &Config,
+) -> (String, (usize, usize)) {
+    let number = config.number_style.format(position);
+    let name = if config.rtl_aware && is_rtl(name) {
+        format!(
+            "{}{}{}",
+            RIGHT_TO_LEFT_ISOLATE, name, POP_DIRECTIONAL_ISOLATE
+        )
     } else {
-        style!(palette.fg, palette.bg).paint(format!(" {} ", text))
+        name.to_string()
+    };
+    let expand = |text: &str| {
+        let flag = |on: bool, text: &str| if on { text.to_string() } else { String::new() };
+        // the name goes in last so braces in it are never expanded
+        text.replace("{index}", &number)
+            .replace("{pane_count}", &pane_count.to_string())
+            .replace("{sync}", &flag(synced, &config.sync_suffix))
+            .replace(
+                "{fullscreen}",
+                &flag(tab.is_fullscreen_active, config.fullscreen_glyph.get()),
+            )
+            .replace(
+                "{floating}",
+                &flag(tab.are_floating_panes_visible, config.floating_glyph.get()),
+            )
+            .replace("{swap_layout}", &swap_layout_label(tab, config))
+            .replace("{name}", &name)
+    };
+    match template.split_once("{index}") {
+        Some((before, after)) => {
+            let start = text_width(&expand(before));
+            let label = format!("{}{}{}", expand(before), number, expand(after));
+            (label, (start, start + text_width(&number)))
+        }
+        None => (expand(template), (0, 0)),
+    }
+}
+
+// the active tab's swap layout name in its brackets, empty when there is none
+pub(crate) fn swap_layout_label(tab: &TabInfo, config: &Config) -> String {
+    let name = tab
+        .active_swap_layout_name
+        .as_deref()
+        .filter(|_| config.show_swap_layout && tab.active);
+    let Some(name) = name else {
+        return String::new();
     };
+    let mut brackets = config.swap_layout_brackets.chars();
+    let open = brackets.next().map(String::from).unwrap_or_default();
+    let close: String = brackets.collect();
+    format!("{}{}{}", open, name, close)
+}
+
+pub(crate) fn shows_sync(tab: &TabInfo, all_synced: bool, config: &Config) -> bool {
+    // the bar itself shows that every tab is synced
+    tab.is_sync_panes_active && !(all_synced && config.sync_all_indicator)
+}
+
+// columns of the number within the label built by tab_label, empty when there is no number
+pub(crate) fn number_region(
+    position: usize,
+    tab_count: usize,
+    name: &str,
+    config: &Config,
+) -> (usize, usize) {
+    if !shows_number(tab_count, config) {
+        return (0, 0);
+    }
+    let number_width = text_width(&config.number_style.format(position));
+    if config.rtl_aware && is_rtl(name) {
+        let start = text_width(name) + 1;
+        (start, start + number_width)
+    } else {
+        (0, number_width)
+    }
+}
+
+pub fn render_tab(
+    text: String,
+    tab: &TabInfo,
+    style: Style,
+    padding: usize,
+    close_glyph: Option<&str>,
+    marker: Option<(&str, Style)>,
+    link: Option<&str>,
+) -> LinePart {
+    let mut tab_text_len = text_width(&text) + 2 * padding;
+    let mut text = format!("{}{}", text, " ".repeat(padding));
+    // the glyph and the space after it make up the close region of the tab
+    if let Some(glyph) = close_glyph {
+        tab_text_len += close_region_width(glyph);
+        text.push_str(glyph);
+        text.push(' ');
+    }
 
-    let tab_styled_text = ANSIStrings(&[tab_styled_text]).to_string();
+    let mut parts = vec![style.paint(" ".repeat(padding))];
+    // the marker keeps its own color, the space after it is part of the tab
+    if let Some((glyph, marker_style)) = marker {
+        tab_text_len += marker_width(glyph);
+        parts.push(marker_style.paint(glyph.to_string()));
+        text.insert(0, ' ');
+    }
+    parts.push(style.paint(text));
+    let mut tab_styled_text = ANSIStrings(&parts).to_string();
+    // the escapes take no cells, so the length stays what is visible
+    if let Some(uri) = link {
+        tab_styled_text = format!(
+            "\u{1b}]8;;{}\u{1b}\\{}\u{1b}]8;;\u{1b}\\",
+            uri, tab_styled_text
+        );
+    }
 
     LinePart {
         part: tab_styled_text,
         len: tab_text_len,
         tab_index: Some(tab.position),
+        kind: PartKind::Tab,
     }
 }
 
-pub fn tab_style(mut tabname: String, tab: &TabInfo, palette: Palette) -> LinePart {
-    if tab.is_sync_panes_active {
-        tabname.push_str(" (Sync)");
+// fnv-1a, so the same name gets the same color on every run and platform
+fn name_hash(name: &str) -> u64 {
+    name.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn hash_color(name: &str, active: bool, palette: Palette, config: &Config) -> Option<PaletteColor> {
+    if !config.hash_colors || (active && !config.hash_colors_on_active) {
+        return None;
     }
+    let default_colors = [
+        palette.red,
+        palette.green,
+        palette.blue,
+        palette.magenta,
+        palette.cyan,
+        palette.orange,
+    ];
+    let colors: &[PaletteColor] = if config.hash_palette.is_empty() {
+        &default_colors
+    } else {
+        &config.hash_palette
+    };
+    Some(colors[(name_hash(name) % colors.len() as u64) as usize])
+}
 
-    render_tab(tabname, tab, palette)
+// black on light backgrounds and white on dark ones, the eight bit palette
+// doesn't say how light its colors are so those keep black
+fn readable_fg(background: PaletteColor, palette: Palette) -> PaletteColor {
+    match background {
+        PaletteColor::Rgb((r, g, b))
+            if 299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b) < 128_000 =>
+        {
+            palette.white
+        }
+        _ => palette.black,
+    }
+}
+
+// what the bar knows about a tab beyond its TabInfo
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct TabMarks {
+    pub all_synced: bool,
+    // had output since it was last focused, only set with activity_indicator
+    pub unread: bool,
+    // under the pointer, only set with enable_hover
+    pub hovered: bool,
+    // waiting for the right click that closes it, only set with confirm_tab_close
+    pub armed: bool,
+    // the terminal showing the bar lost focus, only set with dim_when_unfocused
+    pub unfocused: bool,
+}
+
+// the part drawn for one tab from its label, slot_width is the exact width of
+// a grid slot and mode the current input mode, nothing else is read
+pub(crate) fn tab_style(
+    mut tabname: String,
+    tab: &TabInfo,
+    palette: Palette,
+    config: &Config,
+    slot_width: Option<usize>,
+    mode: InputMode,
+    marks: TabMarks,
+) -> LinePart {
+    // control characters have no width of their own but move the cursor, so
+    // they would shift every column after them
+    tabname.retain(|c| !c.is_control());
+    // the name being typed stays whole, the caret is part of the label so it is
+    // counted in the tab's width
+    if !(tab.active && mode == InputMode::RenameTab) {
+        tabname = ellipsize(&tabname, config.max_tab_width, &config.ellipsis);
+    } else if let Some(caret) = &config.rename_caret {
+        tabname.push_str(caret);
+    }
+    let synced = shows_sync(tab, marks.all_synced, config);
+    // a tab_format places these itself
+    if config.tab_format.is_none() {
+        if synced {
+            tabname.push_str(&config.sync_suffix);
+        }
+        if tab.is_fullscreen_active && config.show_fullscreen_indicator {
+            tabname.push(' ');
+            tabname.push_str(config.fullscreen_glyph.get());
+        }
+        if tab.are_floating_panes_visible && config.show_floating_indicator {
+            tabname.push(' ');
+            tabname.push_str(config.floating_glyph.get());
+        }
+        let swap_layout = swap_layout_label(tab, config);
+        if !swap_layout.is_empty() {
+            tabname.push(' ');
+            tabname.push_str(&swap_layout);
+        }
+        if config.show_other_clients && !tab.other_focused_clients.is_empty() {
+            tabname.push(' ');
+            tabname.push_str(config.other_clients_glyph.get());
+            tabname.push_str(&tab.other_focused_clients.len().to_string());
+        }
+    }
+    if tab.active && config.readonly_modes.contains(&mode) {
+        tabname.push(' ');
+        tabname.push_str(config.readonly_glyph.get());
+    }
+    let padding = tab_padding(tab, config);
+    let close_glyph = config.close_buttons.then(|| config.close_glyph.get());
+    if let Some(width) = slot_width {
+        let close_width = close_glyph.map_or(0, close_region_width);
+        tabname = fit_to_width(&tabname, width.saturating_sub(2 * padding + close_width));
+    }
+    let renaming = mode == InputMode::RenameTab;
+    let highlight = if renaming && tab.active {
+        Some(config.rename_color.unwrap_or(palette.cyan))
+    } else {
+        config
+            .tab_highlight(&tab.name, palette.magenta)
+            .or_else(|| config.tab_color(tab.position + 1, tab.active))
+            .or_else(|| hash_color(&tab.name, tab.active, palette, config))
+    };
+    // the hashed colors run from dark to light, so their text has to follow
+    let highlight_fg = match highlight {
+        Some(color) if highlight == hash_color(&tab.name, tab.active, palette, config) => {
+            readable_fg(color, palette)
+        }
+        _ => palette.black,
+    };
+    // only a configured placeholder is told apart from a real name
+    let placeholder = renaming && tab.active && tab.name.is_empty();
+    let dimmed = (renaming && !tab.active && config.dim_tabs_while_renaming)
+        || (placeholder && config.rename_placeholder.is_some());
+    let mut palette = palette;
+    if config.striped_background && !tab.active {
+        let odd = tab.position % 2 == 1;
+        let stripe = match (odd, config.stripe_even_bg, config.stripe_odd_bg) {
+            (false, Some(color), _) | (true, _, Some(color)) => Some(color.resolve(palette)),
+            (true, _, None) => stripe_color(palette.bg, palette.theme_hue),
+            (false, None, _) => None,
+        };
+        if let Some(stripe) = stripe {
+            palette.bg = stripe;
+        }
+    }
+
+    let style = if marks.armed {
+        style!(palette.black, palette.red).bold()
+    } else if marks.hovered && !tab.active {
+        let hover_bg = config.hover_bg.map_or_else(
+            || stripe_color(palette.bg, palette.theme_hue).unwrap_or(palette.bg),
+            |bg| bg.resolve(palette),
+        );
+        style!(palette.fg, hover_bg).bold()
+    } else if let Some(color) = highlight {
+        let style = style!(highlight_fg, color);
+        if tab.active {
+            style.bold()
+        } else {
+            style
+        }
+    } else if tab.active {
+        let active_bg = config
+            .active_tab_bg
+            .map_or(palette.yellow, |bg| bg.resolve(palette));
+        // a synced active tab keeps its background and takes the sync color as text
+        let active_fg = if synced {
+            sync_tab_bg(palette, config)
+        } else {
+            config
+                .active_tab_fg
+                .map_or(palette.black, |fg| fg.resolve(palette))
+        };
+        let style = style!(active_fg, active_bg);
+        if synced {
+            style.bold()
+        } else {
+            style
+        }
+    } else if synced {
+        style!(
+            config
+                .sync_tab_fg
+                .map_or(palette.black, |fg| fg.resolve(palette)),
+            sync_tab_bg(palette, config)
+        )
+    } else {
+        style!(palette.fg, palette.bg)
+    };
+    let style = if dimmed || (tab.active && marks.unfocused) {
+        style.dimmed()
+    } else {
+        style
+    };
+    let mut style = style;
+    if tab.active {
+        style.is_bold |= config.active_tab_bold;
+        style.is_italic |= config.active_tab_italic;
+        style.is_underline |= config.active_tab_underline;
+    }
+    let marker = marks.unread.then(|| {
+        let color = config
+            .activity_color
+            .map_or(palette.orange, |color| color.resolve(palette));
+        // sits on whatever background the tab ended up with, sync, stripe or hover
+        let mut marker_style = style!(color, palette.bg);
+        marker_style.background = style.background;
+        (
+            config.activity_glyph.get(),
+            if dimmed {
+                marker_style.dimmed()
+            } else {
+                marker_style
+            },
+        )
+    });
+
+    let link = config.hyperlinks.then(|| {
+        config
+            .hyperlink_uri
+            .replace("{index}", &(tab.position + 1).to_string())
+    });
+    render_tab(
+        tabname,
+        tab,
+        style,
+        padding,
+        close_glyph,
+        marker,
+        link.as_deref(),
+    )
+}
+
+fn sync_tab_bg(palette: Palette, config: &Config) -> PaletteColor {
+    config
+        .sync_tab_bg
+        .map_or(palette.green, |bg| bg.resolve(palette))
+}
+
+// a shade close to the background, lighter on dark themes and darker on light
+// ones, the eight bit palette has no close shades so those keep a flat fill
+fn stripe_color(background: PaletteColor, hue: ThemeHue) -> Option<PaletteColor> {
+    const STRIPE_SHIFT: u8 = 12;
+    let PaletteColor::Rgb((r, g, b)) = background else {
+        return None;
+    };
+    let shift = |channel: u8| match hue {
+        ThemeHue::Dark => channel.saturating_add(STRIPE_SHIFT),
+        ThemeHue::Light => channel.saturating_sub(STRIPE_SHIFT),
+    };
+    Some(PaletteColor::Rgb((shift(r), shift(g), shift(b))))
+}
+
+pub(crate) fn tab_padding(tab: &TabInfo, config: &Config) -> usize {
+    // the active tab is lifted out of the other cards with extra padding
+    if config.card_mode && tab.active {
+        config.tab_padding + 1
+    } else {
+        config.tab_padding
+    }
+}
+
+pub(crate) fn marker_width(glyph: &str) -> usize {
+    text_width(glyph) + 1
+}
+
+fn close_region_width(glyph: &str) -> usize {
+    text_width(glyph) + 1
+}
+
+// the tab whose close glyph is under the click, clicks anywhere else on a tab focus it
+pub(crate) fn get_tab_to_close(
+    tab_line: &[LinePart],
+    mouse_click_col: usize,
+    config: &Config,
+) -> Option<usize> {
+    if !config.close_buttons {
+        return None;
+    }
+    let close_width = close_region_width(config.close_glyph.get());
+    let mut start = 0;
+    for tab_line_part in tab_line {
+        let end = start + tab_line_part.len;
+        if mouse_click_col >= start && mouse_click_col < end {
+            if tab_line_part.kind != PartKind::Tab || mouse_click_col + close_width < end {
+                return None;
+            }
+            // tabs are indexed starting from 1 so we need to add 1
+            return tab_line_part.tab_index.map(|idx| idx + 1);
+        }
+        start = end;
+    }
+    None
+}
+
+// where the tab at `position` is once the tab at `source` was moved to `target`
+pub(crate) fn position_after_move(position: usize, source: usize, target: usize) -> usize {
+    if position == source {
+        target
+    } else if source < position && position <= target {
+        position - 1
+    } else if target <= position && position < source {
+        position + 1
+    } else {
+        position
+    }
+}
+
+// the cached synced tabs that are still open and lost their sync since
+pub(crate) fn tabs_to_resync(synced_tabs: &[usize], tabs: &[TabInfo]) -> Vec<usize> {
+    synced_tabs
+        .iter()
+        .copied()
+        .filter(|position| {
+            tabs.iter()
+                .any(|tab| tab.position == *position && !tab.is_sync_panes_active)
+        })
+        .collect()
+}
+
+// zellij only lets plugins close the focused tab, so a background tab is focused
+// and closed before focus goes back to the tab that was active
+pub(crate) fn close_tab(tab_idx: usize, active_tab_idx: usize) {
+    switch_tab_to(tab_idx as u32);
+    close_focused_tab();
+    if let Some(refocus) = refocus_after_close(tab_idx, active_tab_idx) {
+        switch_tab_to(refocus as u32);
+    }
+}
+
+// the tab to go back to after closing tab_idx, the active tab moved down by one
+// if it came after it, tabs are indexed starting from 1
+pub(crate) fn refocus_after_close(tab_idx: usize, active_tab_idx: usize) -> Option<usize> {
+    match active_tab_idx.cmp(&tab_idx) {
+        Ordering::Equal => None,
+        Ordering::Less => Some(active_tab_idx),
+        Ordering::Greater => Some(active_tab_idx - 1),
+    }
+}
+
+pub(crate) fn new_tab_name(template: &str, tab_idx: usize) -> String {
+    template.replace("{n}", &tab_idx.to_string())
+}
+
+// names a tab created by the bar from the template, or lets the user type one
+pub(crate) fn name_new_tab(tab_idx: usize, config: &Config) {
+    if let Some(template) = &config.new_tab_name {
+        rename_tab(tab_idx as u32, new_tab_name(template, tab_idx));
+    } else if config.rename_new_tab {
+        switch_to_input_mode(&InputMode::RenameTab);
+    }
+}
+
+// positions of the tabs that are gone from `new`, tabs carry no id so the names
+// are lined up, the tabs after a closed one move down by one
+pub(crate) fn closed_tabs(old: &[TabInfo], new: &[TabInfo]) -> Vec<usize> {
+    if new.len() >= old.len() {
+        return vec![];
+    }
+    let mut remaining = new.iter().peekable();
+    let mut closed = vec![];
+    for tab in old {
+        if remaining.peek().is_some_and(|next| next.name == tab.name) {
+            remaining.next();
+        } else {
+            closed.push(tab.position);
+        }
+    }
+    closed
+}
+
+// what the bar draws from the panes of one tab, the manifest itself also changes
+// with every title or cursor move, so the focused pane's title is only kept when
+// the bar draws it
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct PaneSummary {
+    position: usize,
+    terminals: usize,
+    plugins: usize,
+    running: bool,
+    focused: Option<(u32, bool, String)>,
+}
+
+pub(crate) fn pane_summary(panes: &PaneManifest, with_title: bool) -> Vec<PaneSummary> {
+    panes
+        .panes
+        .iter()
+        .map(|(position, tab_panes)| PaneSummary {
+            position: *position,
+            terminals: tab_pane_count(panes, *position),
+            plugins: tab_panes.iter().filter(|pane| pane.is_plugin).count(),
+            running: tab_runs_command(panes, *position),
+            focused: tab_panes
+                .iter()
+                .find(|pane| pane.is_focused && !pane.is_suppressed)
+                .map(|pane| {
+                    let title = if with_title {
+                        pane.title.clone()
+                    } else {
+                        String::new()
+                    };
+                    (pane.id, pane.is_plugin, title)
+                }),
+        })
+        .collect()
+}
+
+pub(crate) fn tab_pane_count(panes: &PaneManifest, position: usize) -> usize {
+    panes.panes.get(&position).map_or(0, |panes| {
+        panes.iter().filter(|pane| !pane.is_plugin).count()
+    })
+}
+
+// the cached focused panes of the tabs other than the landing one whose focus
+// moved since, a tab without a focused pane is gone or empty and left alone
+pub(crate) fn panes_to_refocus(
+    tab_panes: &BTreeMap<usize, (u32, bool)>,
+    landing: usize,
+    panes: &PaneManifest,
+) -> Vec<(u32, bool)> {
+    tab_panes
+        .iter()
+        .filter(|(position, _)| **position != landing)
+        .filter(|(position, (id, is_plugin))| {
+            get_focused_pane(**position, panes)
+                .is_some_and(|focused| focused.id != *id || focused.is_plugin != *is_plugin)
+        })
+        .map(|(_, pane)| *pane)
+        .collect()
+}
+
+// where a switch lands in a session that may have lost tabs or panes since its
+// layout was cached, the last tab for a tab that is gone and no particular pane
+// for a pane that is, a session zellij listed without its tabs is taken as cached
+pub(crate) fn landing_focus(
+    session: &SessionInfo,
+    tab_idx: usize,
+    pane: (u32, bool),
+) -> (usize, Option<(u32, bool)>) {
+    if session.tabs.is_empty() {
+        return (tab_idx, Some(pane));
+    }
+    let tab_idx = min(tab_idx, session.tabs.len() - 1);
+    let pane_exists = session.panes.panes.get(&tab_idx).is_some_and(|panes| {
+        panes
+            .iter()
+            .any(|info| info.id == pane.0 && info.is_plugin == pane.1)
+    });
+    (tab_idx, pane_exists.then_some(pane))
+}
+
+// a pane started from a command keeps it in terminal_command until it exits
+pub(crate) fn tab_runs_command(panes: &PaneManifest, position: usize) -> bool {
+    panes.panes.get(&position).is_some_and(|panes| {
+        panes
+            .iter()
+            .any(|pane| !pane.is_plugin && pane.terminal_command.is_some() && !pane.exited)
+    })
+}
+
+pub(crate) fn total_pane_count(panes: &PaneManifest, include_plugins: bool) -> usize {
+    panes
+        .panes
+        .values()
+        .flatten()
+        .filter(|pane| include_plugins || !pane.is_plugin)
+        .count()
+}
+
+// zellij doesn't tell plugins how much a pane printed, a pane moving its cursor or
+// changing its title between two manifests is the closest hint, so this counts
+// the panes of each tab that gave one
+pub(crate) fn output_hints(old: &PaneManifest, new: &PaneManifest) -> Vec<(usize, usize)> {
+    new.panes
+        .iter()
+        .filter_map(|(position, panes)| {
+            let old_panes = old.panes.get(position)?;
+            let hints = panes
+                .iter()
+                .filter(|pane| {
+                    old_panes.iter().any(|old_pane| {
+                        old_pane.id == pane.id
+                            && old_pane.is_plugin == pane.is_plugin
+                            && (old_pane.title != pane.title
+                                || old_pane.cursor_coordinates_in_pane
+                                    != pane.cursor_coordinates_in_pane)
+                    })
+                })
+                .count();
+            Some((*position, hints)).filter(|_| hints > 0)
+        })
+        .collect()
+}
+
+// 0 for no output, then one level per glyph of `levels`, the levels are coarse on
+// purpose since the hints only say that something happened
+pub(crate) fn output_level(hints: usize, levels: usize) -> usize {
+    let level = match hints {
+        0 => 0,
+        1..=3 => 1,
+        4..=15 => 2,
+        _ => 3,
+    };
+    level.min(levels)
+}
+
+const PROGRESS_EIGHTHS: [char; 9] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+// `percent` of `width` cells filled in steps of an eighth of a cell, always
+// exactly `width` columns wide
+pub(crate) fn progress_bar(percent: u8, width: usize) -> String {
+    let eighths = usize::from(percent.min(100)) * width * 8 / 100;
+    (0..width)
+        .map(|cell| PROGRESS_EIGHTHS[eighths.saturating_sub(cell * 8).min(8)])
+        .collect()
+}
+
+// a tab position starting from 1 and a percentage, anything else as the
+// percentage clears the progress of the tab
+pub(crate) fn parse_tab_progress(payload: &str) -> Option<(usize, Option<u8>)> {
+    let (position, percent) = payload.split_once(':')?;
+    let position: usize = position.trim().parse().ok().filter(|p| *p > 0)?;
+    let percent = percent.trim().parse().ok().filter(|p| *p <= 100);
+    Some((position, percent))
+}
+
+// zellij doesn't tell plugins about pane output, so a pane changing its title
+// or exiting is taken as a sign of activity in its tab
+pub(crate) fn tabs_with_activity(old: &PaneManifest, new: &PaneManifest) -> Vec<usize> {
+    new.panes
+        .iter()
+        .filter(|(position, panes)| {
+            let Some(old_panes) = old.panes.get(position) else {
+                return false;
+            };
+            panes.iter().any(|pane| {
+                old_panes.iter().any(|old_pane| {
+                    old_pane.id == pane.id
+                        && old_pane.is_plugin == pane.is_plugin
+                        && (old_pane.title != pane.title || old_pane.exited != pane.exited)
+                })
+            })
+        })
+        .map(|(position, _)| *position)
+        .collect()
+}
+
+// keeps the most recently visited tabs first, dropping the active tab, closed
+// tabs and anything past max_len, the tabs after a closed one are renumbered
+// so they still point at the same tabs
+pub(crate) fn update_tab_history(
+    history: &mut Vec<usize>,
+    previous_tab_idx: usize,
+    active_tab_idx: usize,
+    closed: &[usize],
+    tab_count: usize,
+    max_len: usize,
+) {
+    // tabs are indexed starting from 1, closed holds positions
+    let renumber = |idx: usize| {
+        if idx == 0 || closed.contains(&(idx - 1)) {
+            return None;
+        }
+        Some(
+            idx - closed
+                .iter()
+                .filter(|position| **position < idx - 1)
+                .count(),
+        )
+    };
+    *history = history.iter().filter_map(|idx| renumber(*idx)).collect();
+    if let Some(previous_tab_idx) = renumber(previous_tab_idx) {
+        if previous_tab_idx != active_tab_idx {
+            history.retain(|idx| *idx != previous_tab_idx);
+            history.insert(0, previous_tab_idx);
+        }
+    }
+    history.retain(|idx| *idx != active_tab_idx && *idx <= tab_count);
+    history.truncate(max_len);
+}
+
+// moves one tab from active_tab_idx, wrapping around the ends, and reports
+// whether it wrapped, tabs are indexed starting from 1
+pub(crate) fn next_tab_index(
+    active_tab_idx: usize,
+    tab_count: usize,
+    forward: bool,
+) -> (usize, bool) {
+    if tab_count == 0 {
+        return (active_tab_idx, false);
+    }
+    if forward {
+        if active_tab_idx >= tab_count {
+            (1, true)
+        } else {
+            (active_tab_idx + 1, false)
+        }
+    } else if active_tab_idx <= 1 {
+        (tab_count, true)
+    } else {
+        (active_tab_idx - 1, false)
+    }
 }
 
 pub(crate) fn get_tab_to_focus(
@@ -45,6 +839,106 @@ pub(crate) fn get_tab_to_focus(
     None
 }
 
+// the tab whose name, rather than its number, is under the click, `number_regions`
+// holds the columns of each number relative to the start of its tab
+pub(crate) fn get_tab_to_rename(
+    tab_line: &[LinePart],
+    mouse_click_col: usize,
+    number_regions: &BTreeMap<usize, (usize, usize)>,
+) -> Option<usize> {
+    let mut start = 0;
+    for tab_line_part in tab_line {
+        let end = start + tab_line_part.len;
+        if mouse_click_col >= start && mouse_click_col < end {
+            let tab_index = tab_line_part
+                .tab_index
+                .filter(|_| tab_line_part.kind == PartKind::Tab)?;
+            let (number_start, number_end) = number_regions.get(&tab_index)?;
+            let offset = mouse_click_col - start;
+            if offset >= *number_start && offset < *number_end {
+                return None;
+            }
+            // tabs are indexed starting from 1 so we need to add 1
+            return Some(tab_index + 1);
+        }
+        start = end;
+    }
+    None
+}
+
+// scrolling over an overflow indicator moves by a whole page of visible tabs
+pub(crate) fn get_scroll_step(
+    tab_line: &[LinePart],
+    hover_col: Option<usize>,
+    page_scroll_on_overflow: bool,
+) -> usize {
+    if !page_scroll_on_overflow {
+        return 1;
+    }
+    let hovered_part = hover_col.and_then(|col| get_clicked_line_part(tab_line, col));
+    match hovered_part {
+        Some(part) if part.kind == PartKind::Overflow => max(
+            tab_line
+                .iter()
+                .filter(|part| part.kind == PartKind::Tab)
+                .count(),
+            1,
+        ),
+        _ => 1,
+    }
+}
+
+// the tab a scroll of `step` tabs lands on, stopping at the ends unless it wraps,
+// tabs are indexed starting from 1
+pub(crate) fn scroll_target(
+    active_tab_idx: usize,
+    tab_count: usize,
+    step: usize,
+    forward: bool,
+    wrap: bool,
+) -> usize {
+    if wrap && tab_count > 0 {
+        // shift to 0 for the modulo
+        let step = step % tab_count;
+        let position = active_tab_idx.saturating_sub(1);
+        if forward {
+            (position + step) % tab_count + 1
+        } else {
+            (position + tab_count - step) % tab_count + 1
+        }
+    } else if forward {
+        min(active_tab_idx + step, tab_count)
+    } else {
+        max(active_tab_idx.saturating_sub(step), 1)
+    }
+}
+
+// position of the tab under the column, breadcrumbs and markers don't count
+pub(crate) fn get_clicked_tab(tab_line: &[LinePart], mouse_click_col: usize) -> Option<usize> {
+    get_clicked_line_part(tab_line, mouse_click_col)
+        .filter(|part| part.kind == PartKind::Tab)
+        .and_then(|part| part.tab_index)
+}
+
+// whether the clicked overflow marker is to the right of the visible tabs,
+// None when the click isn't on a marker
+pub(crate) fn get_overflow_direction(
+    tab_line: &[LinePart],
+    mouse_click_col: usize,
+) -> Option<bool> {
+    let mut start = 0;
+    let mut after_tabs = false;
+    for tab_line_part in tab_line {
+        let end = start + tab_line_part.len;
+        if mouse_click_col >= start && mouse_click_col < end {
+            return (tab_line_part.kind == PartKind::Overflow).then_some(after_tabs);
+        }
+        after_tabs |= tab_line_part.kind == PartKind::Tab;
+        start = end;
+    }
+    None
+}
+
 pub(crate) fn get_clicked_line_part(
     tab_line: &[LinePart],
     mouse_click_col: usize,
@@ -58,3 +952,674 @@ pub(crate) fn get_clicked_line_part(
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rtl_names_take_their_number_on_the_right() {
+        let config = Config {
+            rtl_aware: true,
+            ..Default::default()
+        };
+        let label = tab_label(3, 4, "שלום", &config);
+        assert_eq!(label, "\u{2067}שלום\u{2069} 3");
+        // the isolates take no cells
+        assert_eq!(text_width(&label), 6);
+        assert_eq!(number_region(3, 4, "שלום", &config), (5, 6));
+    }
+
+    #[test]
+    fn ltr_names_and_rtl_off_keep_the_number_first() {
+        let rtl = Config {
+            rtl_aware: true,
+            ..Default::default()
+        };
+        assert_eq!(tab_label(3, 4, "work", &rtl), "3 work");
+        assert_eq!(number_region(3, 4, "work", &rtl), (0, 1));
+        let config = Config::default();
+        assert_eq!(tab_label(3, 4, "שלום", &config), "3 שלום");
+        assert_eq!(number_region(3, 4, "שלום", &config), (0, 1));
+    }
+
+    fn named(names: &[&str]) -> Vec<TabInfo> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(position, name)| TabInfo {
+                position,
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn closed_tabs_are_found_by_name() {
+        let old = named(&["a", "b", "c", "d"]);
+        assert_eq!(closed_tabs(&old, &named(&["a", "c", "d"])), vec![1]);
+        assert_eq!(closed_tabs(&old, &named(&["a", "b", "c"])), vec![3]);
+        assert_eq!(closed_tabs(&old, &named(&["b", "d"])), vec![0, 2]);
+        // nothing closed when the count didn't drop
+        assert!(closed_tabs(&old, &named(&["a", "x", "c", "d"])).is_empty());
+    }
+
+    #[test]
+    fn history_follows_tab_switches() {
+        let mut history = vec![];
+        update_tab_history(&mut history, 0, 1, &[], 5, 3);
+        update_tab_history(&mut history, 1, 3, &[], 5, 3);
+        update_tab_history(&mut history, 3, 5, &[], 5, 3);
+        assert_eq!(history, vec![3, 1]);
+        update_tab_history(&mut history, 5, 2, &[], 5, 3);
+        assert_eq!(history, vec![5, 3, 1]);
+        // going back moves the tab to the front and never shows the active tab
+        update_tab_history(&mut history, 2, 3, &[], 5, 3);
+        assert_eq!(history, vec![2, 5, 1]);
+    }
+
+    #[test]
+    fn history_drops_a_closed_tab_and_renumbers_the_ones_after_it() {
+        // visited 1, 4, 5 and then 2, with 5 tabs open
+        let mut history = vec![5, 4, 1];
+        // tab 3 is closed while tab 2 stays active
+        update_tab_history(&mut history, 2, 2, &[2], 4, 3);
+        assert_eq!(history, vec![4, 3, 1]);
+        // tab 4, which used to be 5, is closed and focus lands on tab 3
+        update_tab_history(&mut history, 2, 3, &[3], 3, 3);
+        assert_eq!(history, vec![2, 1]);
+    }
+
+    #[test]
+    fn closing_the_active_tab_keeps_it_out_of_the_history() {
+        let mut history = vec![1];
+        // tab 3 was active and is closed, focus lands on tab 2
+        update_tab_history(&mut history, 3, 2, &[2], 2, 3);
+        assert_eq!(history, vec![1]);
+    }
+
+    fn part(len: usize, tab_index: Option<usize>, kind: PartKind) -> LinePart {
+        LinePart {
+            part: " ".repeat(len),
+            len,
+            tab_index,
+            kind,
+        }
+    }
+
+    // ← +2 | tab 3 | tab 4 | tab 5 | → +5
+    fn overflowing_line() -> Vec<LinePart> {
+        vec![
+            part(5, None, PartKind::Overflow),
+            part(7, Some(2), PartKind::Tab),
+            part(7, Some(3), PartKind::Tab),
+            part(7, Some(4), PartKind::Tab),
+            part(5, None, PartKind::Overflow),
+        ]
+    }
+
+    #[test]
+    fn scrolling_over_an_indicator_pages_by_the_visible_tabs() {
+        let line = overflowing_line();
+        assert_eq!(get_scroll_step(&line, Some(0), true), 3);
+        assert_eq!(get_scroll_step(&line, Some(30), true), 3);
+        // over a tab, or with paging off, it is one tab
+        assert_eq!(get_scroll_step(&line, Some(6), true), 1);
+        assert_eq!(get_scroll_step(&line, Some(0), false), 1);
+        assert_eq!(get_scroll_step(&line, None, true), 1);
+    }
+
+    #[test]
+    fn a_page_stops_at_the_ends_of_the_list() {
+        assert_eq!(scroll_target(4, 10, 3, true, false), 7);
+        assert_eq!(scroll_target(9, 10, 3, true, false), 10);
+        assert_eq!(scroll_target(10, 10, 3, true, false), 10);
+        assert_eq!(scroll_target(2, 10, 3, false, false), 1);
+        assert_eq!(scroll_target(1, 10, 3, false, false), 1);
+    }
+
+    #[test]
+    fn a_page_wraps_around_with_wrap_tabs() {
+        assert_eq!(scroll_target(9, 10, 3, true, true), 2);
+        assert_eq!(scroll_target(2, 10, 3, false, true), 9);
+        // a page longer than the list wraps by what is left over
+        assert_eq!(scroll_target(1, 4, 9, true, true), 2);
+    }
+
+    #[test]
+    fn new_tabs_are_named_from_the_template() {
+        assert_eq!(new_tab_name("tab-{n}", 3), "tab-3");
+        assert_eq!(new_tab_name("{n}: {n}", 12), "12: 12");
+        // a template without the placeholder names every tab the same
+        assert_eq!(new_tab_name("scratch", 4), "scratch");
+    }
+
+    fn tab(position: usize) -> TabInfo {
+        TabInfo {
+            position,
+            name: format!("tab {}", position + 1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn landing_focus_keeps_a_tab_and_pane_that_exist() {
+        let session = session(3, vec![(1, vec![pane(4, false), pane(5, true)])]);
+        assert_eq!(landing_focus(&session, 1, (5, true)), (1, Some((5, true))));
+    }
+
+    #[test]
+    fn landing_focus_clamps_a_missing_tab_to_the_last_one() {
+        let session = session(2, vec![(1, vec![pane(4, false)])]);
+        assert_eq!(
+            landing_focus(&session, 5, (4, false)),
+            (1, Some((4, false)))
+        );
+    }
+
+    #[test]
+    fn landing_focus_drops_a_missing_pane() {
+        let session = session(2, vec![(0, vec![pane(4, false)])]);
+        assert_eq!(landing_focus(&session, 0, (9, false)), (0, None));
+        // same id, but a plugin is a different pane
+        assert_eq!(landing_focus(&session, 0, (4, true)), (0, None));
+    }
+
+    #[test]
+    fn landing_focus_trusts_the_cache_for_a_session_without_tabs() {
+        let session = session(0, vec![]);
+        assert_eq!(
+            landing_focus(&session, 3, (9, false)),
+            (3, Some((9, false)))
+        );
+    }
+
+    #[test]
+    fn only_open_tabs_that_lost_their_sync_are_synced_again() {
+        let tabs: Vec<TabInfo> = (0..3)
+            .map(|position| TabInfo {
+                is_sync_panes_active: position == 1,
+                ..tab(position)
+            })
+            .collect();
+        // 1 is still synced and 5 was closed
+        assert_eq!(tabs_to_resync(&[0, 1, 5], &tabs), vec![0]);
+        assert!(tabs_to_resync(&[], &tabs).is_empty());
+    }
+
+    #[test]
+    fn a_lone_tab_drops_its_number() {
+        let config = Config {
+            hide_single_tab_number: true,
+            ..Default::default()
+        };
+        assert_eq!(tab_label(1, 1, "work", &config), "work");
+        assert_eq!(tab_label(1, 2, "work", &config), "1 work");
+        assert_eq!(tab_label(2, 2, "play", &config), "2 play");
+        // without the option a lone tab keeps its number
+        assert_eq!(tab_label(1, 1, "work", &Config::default()), "1 work");
+    }
+
+    #[test]
+    fn cycling_past_either_end_reports_the_wrap() {
+        assert_eq!(next_tab_index(2, 4, true), (3, false));
+        assert_eq!(next_tab_index(4, 4, true), (1, true));
+        assert_eq!(next_tab_index(2, 4, false), (1, false));
+        assert_eq!(next_tab_index(1, 4, false), (4, true));
+        // a single tab wraps onto itself and no tabs stay put
+        assert_eq!(next_tab_index(1, 1, true), (1, true));
+        assert_eq!(next_tab_index(0, 0, true), (0, false));
+    }
+
+    #[test]
+    fn the_tab_being_renamed_gets_the_editing_style() {
+        let config = Config {
+            rename_color: Some(PaletteColor::Rgb((1, 2, 3))),
+            ..Default::default()
+        };
+        let tab = TabInfo {
+            name: "work".to_string(),
+            active: true,
+            ..Default::default()
+        };
+        let render = |mode| {
+            let part = tab_style(
+                "1 work".to_string(),
+                &tab,
+                Palette::default(),
+                &config,
+                None,
+                mode,
+                TabMarks::default(),
+            );
+            part.part
+        };
+        let before = render(InputMode::Normal);
+        let renaming = render(InputMode::RenameTab);
+        assert!(renaming.contains("48;2;1;2;3"));
+        assert!(!before.contains("48;2;1;2;3"));
+        // leaving rename mode draws the tab as it was
+        assert_eq!(render(InputMode::Normal), before);
+    }
+
+    #[test]
+    fn the_readonly_indicator_follows_the_configured_modes() {
+        let config = Config {
+            readonly_modes: vec![InputMode::Scroll, InputMode::Search],
+            ..Default::default()
+        };
+        let tab = TabInfo {
+            active: true,
+            ..Default::default()
+        };
+        let label = |tab: &TabInfo, mode| {
+            let part = tab_style(
+                "1 work".to_string(),
+                tab,
+                Palette::default(),
+                &config,
+                None,
+                mode,
+                TabMarks::default(),
+            );
+            part.part.contains("1 work RO")
+        };
+        assert!(label(&tab, InputMode::Scroll));
+        assert!(label(&tab, InputMode::Search));
+        assert!(!label(&tab, InputMode::Normal));
+        // only the active tab is the one being read
+        let background = TabInfo::default();
+        assert!(!label(&background, InputMode::Scroll));
+    }
+
+    #[test]
+    fn names_hash_the_same_on_every_run() {
+        // the published fnv-1a 64 values
+        assert_eq!(name_hash(""), 0xcbf29ce484222325);
+        assert_eq!(name_hash("a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn hashed_colors_follow_the_name() {
+        let config = Config {
+            hash_colors: true,
+            hash_palette: (1..=8).map(PaletteColor::EightBit).collect(),
+            ..Default::default()
+        };
+        let color = |name: &str| hash_color(name, false, Palette::default(), &config);
+        assert_eq!(color("logs"), color("logs"));
+        let names = ["editor", "logs", "build", "shell", "db", "web"];
+        let mut colors: Vec<_> = names.iter().map(|name| color(name)).collect();
+        colors.sort_by_key(|color| format!("{:?}", color));
+        colors.dedup();
+        assert!(colors.len() > 1);
+        // the active tab keeps its own color unless asked
+        assert_eq!(hash_color("logs", true, Palette::default(), &config), None);
+    }
+
+    #[test]
+    fn focus_goes_back_to_the_tab_that_was_active() {
+        assert_eq!(refocus_after_close(2, 4), Some(3));
+        assert_eq!(refocus_after_close(4, 2), Some(2));
+        // closing the active tab leaves focus where zellij puts it
+        assert_eq!(refocus_after_close(3, 3), None);
+    }
+
+    fn close_line(config: &Config) -> Vec<LinePart> {
+        (0..2)
+            .map(|position| {
+                let tab = TabInfo {
+                    active: position == 0,
+                    ..tab(position)
+                };
+                tab_style(
+                    tab.name.clone(),
+                    &tab,
+                    Palette::default(),
+                    config,
+                    None,
+                    InputMode::Normal,
+                    TabMarks::default(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn close_glyph_clicks_close_and_the_rest_focuses() {
+        let config = Config {
+            close_buttons: true,
+            ..Default::default()
+        };
+        let line = close_line(&config);
+        let close_width = close_region_width(config.close_glyph.get());
+        let second_start = line[0].len;
+        let second_end = second_start + line[1].len;
+        // the glyph and the space after it close
+        assert_eq!(
+            get_tab_to_close(&line, second_end - close_width, &config),
+            Some(2)
+        );
+        assert_eq!(get_tab_to_close(&line, second_end - 1, &config), Some(2));
+        // anything before it focuses
+        let before_glyph = second_end - close_width - 1;
+        assert_eq!(get_tab_to_close(&line, before_glyph, &config), None);
+        assert_eq!(get_tab_to_focus(&line, 1, before_glyph), Some(2));
+        assert_eq!(get_tab_to_close(&line, second_start, &config), None);
+        assert_eq!(get_tab_to_focus(&line, 1, second_start), Some(2));
+    }
+
+    #[test]
+    fn close_glyph_does_nothing_without_close_buttons() {
+        let config = Config::default();
+        let line = close_line(&config);
+        let end = line[0].len + line[1].len;
+        assert_eq!(get_tab_to_close(&line, end - 1, &config), None);
+    }
+
+    fn pane(id: u32, is_plugin: bool) -> PaneInfo {
+        PaneInfo {
+            id,
+            is_plugin,
+            ..Default::default()
+        }
+    }
+
+    fn session(tabs: usize, panes: Vec<(usize, Vec<PaneInfo>)>) -> SessionInfo {
+        SessionInfo {
+            name: "work".to_string(),
+            tabs: (0..tabs).map(tab).collect(),
+            panes: PaneManifest {
+                panes: panes.into_iter().collect(),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn the_session_pane_count_spans_every_tab() {
+        let panes = session(
+            3,
+            vec![
+                (0, vec![pane(1, false), pane(2, false), pane(1, true)]),
+                (1, vec![pane(3, false)]),
+                (2, vec![pane(4, false), pane(2, true)]),
+            ],
+        )
+        .panes;
+        assert_eq!(total_pane_count(&panes, false), 4);
+        assert_eq!(total_pane_count(&panes, true), 6);
+        assert_eq!(total_pane_count(&PaneManifest::default(), true), 0);
+    }
+
+    #[test]
+    fn only_moved_focus_outside_the_landing_tab_is_refocused() {
+        let focused = |id: u32| PaneInfo {
+            is_focused: true,
+            ..pane(id, false)
+        };
+        let panes = PaneManifest {
+            panes: [
+                (0, vec![pane(1, false), focused(2)]),
+                (1, vec![focused(3)]),
+                (2, vec![focused(4)]),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        // 0 moved away from 1, 1 is where the client landed, 2 didn't move and 3 is gone
+        let tab_panes = BTreeMap::from([
+            (0, (1, false)),
+            (1, (9, false)),
+            (2, (4, false)),
+            (3, (5, false)),
+        ]);
+        assert_eq!(panes_to_refocus(&tab_panes, 1, &panes), vec![(1, false)]);
+        // a plugin with the same id is a different pane
+        let tab_panes = BTreeMap::from([(2, (4, true))]);
+        assert_eq!(panes_to_refocus(&tab_panes, 1, &panes), vec![(4, true)]);
+    }
+
+    #[test]
+    fn number_and_name_clicks_on_one_tab_go_different_ways() {
+        let config = Config::default();
+        let mut number_regions = BTreeMap::new();
+        let tab_line: Vec<LinePart> = ["work", "play"]
+            .iter()
+            .enumerate()
+            .map(|(position, name)| {
+                let tab = TabInfo {
+                    position,
+                    name: name.to_string(),
+                    active: position == 0,
+                    ..Default::default()
+                };
+                let (start, end) = number_region(position + 1, 2, name, &config);
+                let padding = tab_padding(&tab, &config);
+                number_regions.insert(position, (start + padding, end + padding));
+                tab_style(
+                    tab_label(position + 1, 2, name, &config),
+                    &tab,
+                    Palette::default(),
+                    &config,
+                    None,
+                    InputMode::Normal,
+                    TabMarks::default(),
+                )
+            })
+            .collect();
+        // " 2 play ", the number is a column in from the start of the tab
+        let number_col = tab_line[0].len + 1;
+        let name_col = tab_line[0].len + 3;
+        assert_eq!(
+            get_tab_to_rename(&tab_line, number_col, &number_regions),
+            None
+        );
+        assert_eq!(get_tab_to_focus(&tab_line, 1, number_col), Some(2));
+        assert_eq!(
+            get_tab_to_rename(&tab_line, name_col, &number_regions),
+            Some(2)
+        );
+        // the padding around the label belongs to the name
+        assert_eq!(
+            get_tab_to_rename(&tab_line, tab_line[0].len, &number_regions),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn output_hints_count_the_panes_that_moved() {
+        let titled = |id: u32, title: &str| PaneInfo {
+            id,
+            title: title.to_string(),
+            ..Default::default()
+        };
+        let old = session(
+            2,
+            vec![
+                (0, vec![titled(1, "vim"), titled(2, "bash")]),
+                (1, vec![titled(3, "htop")]),
+            ],
+        )
+        .panes;
+        let new = session(
+            2,
+            vec![
+                (0, vec![titled(1, "vim main.rs"), titled(2, "cargo")]),
+                (1, vec![titled(3, "htop")]),
+            ],
+        )
+        .panes;
+        assert_eq!(output_hints(&old, &new), vec![(0, 2)]);
+        assert_eq!(output_hints(&new, &new), vec![]);
+    }
+
+    #[test]
+    fn output_is_bucketed_into_coarse_levels() {
+        let levels: Vec<usize> = [0, 1, 3, 4, 15, 16, 500]
+            .iter()
+            .map(|hints| output_level(*hints, 3))
+            .collect();
+        assert_eq!(levels, vec![0, 1, 1, 2, 2, 3, 3]);
+        // fewer glyphs cap the level
+        assert_eq!(output_level(500, 1), 1);
+        assert_eq!(output_level(500, 0), 0);
+    }
+
+    fn striped(position: usize, palette: Palette) -> LinePart {
+        let config = Config {
+            striped_background: true,
+            ..Default::default()
+        };
+        tab_style(
+            "1 work".to_string(),
+            &tab(position),
+            palette,
+            &config,
+            None,
+            InputMode::Normal,
+            TabMarks::default(),
+        )
+    }
+
+    #[test]
+    fn inactive_tabs_alternate_their_background() {
+        let palette = Palette {
+            bg: PaletteColor::Rgb((30, 30, 30)),
+            theme_hue: ThemeHue::Dark,
+            ..Default::default()
+        };
+        let even = striped(2, palette);
+        let odd = striped(3, palette);
+        assert!(even.part.contains("48;2;30;30;30"));
+        assert!(odd.part.contains("48;2;42;42;42"));
+        // the stripe only changes the color, not the width
+        assert_eq!(even.len, odd.len);
+    }
+
+    #[test]
+    fn stripes_fall_back_to_a_flat_background() {
+        // the eight bit palette can't be shifted, so every tab keeps the same bg
+        let palette = Palette {
+            bg: PaletteColor::EightBit(236),
+            ..Default::default()
+        };
+        assert_eq!(striped(2, palette).part, striped(3, palette).part);
+    }
+
+    #[test]
+    fn progress_fills_eighths_of_a_cell() {
+        assert_eq!(progress_bar(0, 4), "    ");
+        assert_eq!(progress_bar(30, 4), "█▏  ");
+        assert_eq!(progress_bar(50, 4), "██  ");
+        assert_eq!(progress_bar(100, 4), "████");
+        // anything past 100 is full
+        assert_eq!(progress_bar(250, 2), "██");
+    }
+
+    #[test]
+    fn progress_is_always_its_width() {
+        for percent in [0, 1, 13, 50, 99, 100] {
+            for width in [1, 4, 10] {
+                assert_eq!(text_width(&progress_bar(percent, width)), width);
+            }
+        }
+        let label = format!("1 work {}", progress_bar(42, 5));
+        let part = tab_style(
+            label,
+            &tab(0),
+            Palette::default(),
+            &Config::default(),
+            None,
+            InputMode::Normal,
+            TabMarks::default(),
+        );
+        assert_eq!(part.len, text_width("1 work ") + 5 + 2);
+    }
+
+    #[test]
+    fn progress_payloads_set_or_clear_a_tab() {
+        assert_eq!(parse_tab_progress("2:40"), Some((2, Some(40))));
+        assert_eq!(parse_tab_progress(" 3 : 100 "), Some((3, Some(100))));
+        assert_eq!(parse_tab_progress("2:-1"), Some((2, None)));
+        assert_eq!(parse_tab_progress("2:101"), Some((2, None)));
+        assert_eq!(parse_tab_progress("0:40"), None);
+        assert_eq!(parse_tab_progress("40"), None);
+    }
+
+    #[test]
+    fn wide_and_zero_width_characters_take_their_cells() {
+        assert_eq!(text_width("日本語"), 6);
+        assert_eq!(text_width("👍"), 2);
+        // e followed by a combining acute accent is one cell
+        assert_eq!(text_width("cafe\u{301}"), 4);
+    }
+
+    #[test]
+    fn clicks_land_on_the_last_tab_after_wide_names() {
+        let config = Config::default();
+        let names = ["日本語", "👍", "cafe\u{301}"];
+        let tab_line: Vec<LinePart> = names
+            .iter()
+            .enumerate()
+            .map(|(position, name)| {
+                let tab = TabInfo {
+                    position,
+                    name: name.to_string(),
+                    active: position == 0,
+                    ..Default::default()
+                };
+                tab_style(
+                    tab_label(position + 1, names.len(), name, &config),
+                    &tab,
+                    Palette::default(),
+                    &config,
+                    None,
+                    InputMode::Normal,
+                    TabMarks::default(),
+                )
+            })
+            .collect();
+        // " 1 日本語 ", " 2 👍 " and " 3 café "
+        let lens: Vec<usize> = tab_line.iter().map(|part| part.len).collect();
+        assert_eq!(lens, vec![10, 6, 8]);
+        assert_eq!(get_tab_to_focus(&tab_line, 1, 16), Some(3));
+        assert_eq!(get_tab_to_focus(&tab_line, 1, 23), Some(3));
+        assert_eq!(get_tab_to_focus(&tab_line, 1, 15), Some(2));
+        assert_eq!(get_tab_to_focus(&tab_line, 1, 24), None);
+    }
+
+    #[test]
+    fn a_move_shifts_the_tabs_between_source_and_target() {
+        // 1 moved right to 3
+        let after: Vec<usize> = (0..5)
+            .map(|position| position_after_move(position, 1, 3))
+            .collect();
+        assert_eq!(after, vec![0, 3, 1, 2, 4]);
+        // 3 moved left to 1
+        let after: Vec<usize> = (0..5)
+            .map(|position| position_after_move(position, 3, 1))
+            .collect();
+        assert_eq!(after, vec![0, 2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn a_title_change_only_counts_when_the_title_is_drawn() {
+        let manifest = |title: &str| PaneManifest {
+            panes: [(
+                0,
+                vec![PaneInfo {
+                    is_focused: true,
+                    title: title.to_string(),
+                    ..pane(1, false)
+                }],
+            )]
+            .into_iter()
+            .collect(),
+        };
+        let (vim, shell) = (manifest("vim"), manifest("zsh"));
+        assert_eq!(pane_summary(&vim, false), pane_summary(&shell, false));
+        assert_ne!(pane_summary(&vim, true), pane_summary(&shell, true));
+    }
+}